@@ -1,15 +1,17 @@
-use std::{collections::HashMap, ffi::OsString, fmt::Display, path::PathBuf};
+use std::{borrow::Cow, collections::HashMap, ffi::OsString, fmt::Display, os::fd::RawFd};
 
 use rustyline::{Editor, history::FileHistory};
 
 use crate::{
     AstError, ParsedPair, TrshResult,
-    builtins::{BUILTINS, CmdName},
+    builtins::{Builtin, CmdName},
     prsr::Rule,
 };
 
 #[derive(Debug, Clone)]
 pub struct SimpleCommand {
+    /// `VAR=value` words before the name, only set for this one command
+    pub assignments: Vec<Assignment>,
     pub name: CmdName,
     pub args: Vec<CmdArg>,
     pub redirections: Vec<Redirection>,
@@ -19,6 +21,8 @@ pub struct SimpleCommand {
 pub enum Token {
     Word(String),
     Quote(String),
+    /// `'...'`, never expanded
+    SingleQuote(String),
     VarExp(String),
     Eq,
     Neq,
@@ -30,9 +34,38 @@ pub enum Redirection {
     Input(String),
     TruncRight(String),
     HereDoc(String),
+    /// `>&fd`, the target is expanded when the command runs
+    DupOut(String),
+    /// `<&fd`
+    DupIn(String),
+    /// `n>file`, `n>&m` and the like: the redirection applies to fd `n` rather
+    /// than stdin or stdout
+    Fd(RawFd, Box<Self>),
 }
 
 impl Redirection {
+    fn new(p: ParsedPair) -> Self {
+        let rule = p.as_rule();
+        let target = p.into_inner().next().unwrap().as_str().to_owned();
+        match rule {
+            Rule::APPEN_R => Self::AppendRight(target),
+            Rule::INPUT => Self::Input(target),
+            Rule::TRUNC_R => Self::TruncRight(target),
+            Rule::DUP_OUT => Self::DupOut(target),
+            Rule::DUP_IN => Self::DupIn(target),
+            r => unreachable!("{r:?}"),
+        }
+    }
+
+    /// The fd a redirection without a number applies to
+    pub fn default_fd(&self) -> RawFd {
+        match self {
+            Self::Input(_) | Self::HereDoc(_) | Self::DupIn(_) => 0,
+            Self::AppendRight(_) | Self::TruncRight(_) | Self::DupOut(_) => 1,
+            Self::Fd(fd, _) => *fd,
+        }
+    }
+
     pub fn load_heredoc(delim: String, rl: &mut Option<&mut Editor<(), FileHistory>>) -> Self {
         if let Some(r) = rl {
             let mut input_str = String::new();
@@ -62,6 +95,9 @@ impl Display for Redirection {
             Redirection::Input(s) => write!(f, "< {s}"),
             Redirection::TruncRight(s) => write!(f, "> {s}"),
             Redirection::HereDoc(s) => write!(f, "<<{s}"),
+            Redirection::DupOut(s) => write!(f, ">&{s}"),
+            Redirection::DupIn(s) => write!(f, "<&{s}"),
+            Redirection::Fd(fd, r) => write!(f, "{fd}{r}"),
         }
     }
 }
@@ -71,7 +107,9 @@ impl Display for Token {
         match self {
             Token::Eq => write!(f, "="),
             Token::Neq => write!(f, "!="),
-            Token::VarExp(s) | Token::Word(s) | Token::Quote(s) => write!(f, "{s}"),
+            Token::VarExp(s) | Token::Word(s) | Token::Quote(s) | Token::SingleQuote(s) => {
+                write!(f, "{s}")
+            }
         }
     }
 }
@@ -81,24 +119,24 @@ impl Token {
         match a.as_rule() {
             Rule::WORD => Self::Word(a.as_str().to_string()),
             Rule::QUOTE => Self::Quote(a.as_str().trim_matches('"').to_string()),
+            Rule::SQUOTE => Self::SingleQuote(a.as_str().trim_matches('\'').to_string()),
+            Rule::assign_lit | Rule::JOB_SPEC | Rule::DASH => Self::Word(a.as_str().to_string()),
             Rule::EQ => Self::Eq,
             Rule::NEQ => Self::Neq,
             Rule::arg => Self::new(a.into_inner().next().unwrap()),
             Rule::VARIABLE_EXPANSION => {
                 Self::VarExp(a.as_str().strip_prefix("$").unwrap().to_string())
             }
+            Rule::PARAM_EXPANSION => Self::VarExp(
+                a.as_str()
+                    .strip_prefix("${")
+                    .and_then(|s| s.strip_suffix("}"))
+                    .unwrap()
+                    .to_string(),
+            ),
             r => panic!("{r:?}"),
         }
     }
-    pub fn as_str(&self) -> &str {
-        match self {
-            Token::Word(s) => s,
-            Token::VarExp(s) => s,
-            Token::Quote(s) => s,
-            Token::Eq => "=",
-            Token::Neq => "!=",
-        }
-    }
 }
 
 impl std::borrow::Borrow<str> for Token {
@@ -107,6 +145,7 @@ impl std::borrow::Borrow<str> for Token {
             Token::Word(s) => s,
             Token::VarExp(s) => s,
             Token::Quote(s) => s,
+            Token::SingleQuote(s) => s,
             Token::Eq => "=",
             Token::Neq => "!=",
         }
@@ -118,6 +157,7 @@ impl AsRef<std::ffi::OsStr> for Token {
         match self {
             Token::Word(s) => std::ffi::OsStr::new(s),
             Token::Quote(s) => std::ffi::OsStr::new(s),
+            Token::SingleQuote(s) => std::ffi::OsStr::new(s),
             Token::VarExp(s) => std::ffi::OsStr::new(s),
             Token::Eq => std::ffi::OsStr::new("="),
             Token::Neq => std::ffi::OsStr::new("!="),
@@ -132,38 +172,43 @@ impl SimpleCommand {
         env: (&HashMap<String, String>, &HashMap<String, String>),
         rl: &mut Option<&mut Editor<(), FileHistory>>,
     ) -> TrshResult<Self> {
-        let mut parts = rule.into_inner();
+        let mut parts = rule.into_inner().peekable();
 
+        let mut assignments = Vec::new();
+        while let Some(a) = parts.next_if(|p| p.as_rule() == Rule::assignment) {
+            assignments.push(Assignment::new(a));
+        }
         let parts_cmd = parts.next().unwrap();
         let parts_name = parts_cmd.as_str().trim();
         // println!("{parts_name}");
-        let name = if parts_name.contains("/") {
-            CmdName::Path(PathBuf::from(parts_name))
-        } else if let Some(cmd) = env.0.get(parts_name) {
-            CmdName::Alias(cmd.clone())
-        } else if let Some(func) = env.1.get(parts_name) {
-            CmdName::Function(func.clone())
-        } else {
-            match BUILTINS.get(parts_name) {
-                Some(builtin) => CmdName::Builtin(*builtin),
-                None => CmdName::Unknown(parts_name.to_owned()),
-            }
-        };
+        let name = CmdName::new(parts_name, env);
+        let decl = parts_cmd.as_rule() == Rule::decl_name;
 
         let mut redirections = Vec::new();
         let mut tokens = Vec::new();
+        let mut args: Vec<CmdArg> = Vec::new();
         for p in parts {
             match p.as_rule() {
-                Rule::arg | Rule::VARIABLE_EXPANSION => tokens.push(Token::new(p)),
-                Rule::APPEN_R => redirections.push(Redirection::AppendRight(
-                    p.into_inner().next().unwrap().as_str().to_owned(),
-                )),
-                Rule::INPUT => redirections.push(Redirection::Input(
-                    p.into_inner().next().unwrap().as_str().to_owned(),
-                )),
-                Rule::TRUNC_R => redirections.push(Redirection::TruncRight(
-                    p.into_inner().next().unwrap().as_str().to_owned(),
-                )),
+                // after `declare` and the like, each argument stands alone
+                Rule::arg | Rule::VARIABLE_EXPANSION | Rule::PARAM_EXPANSION if decl => {
+                    args.push(CmdArg::from(Token::new(p)))
+                }
+                Rule::arg | Rule::VARIABLE_EXPANSION | Rule::PARAM_EXPANSION => {
+                    tokens.push(Token::new(p))
+                }
+                Rule::APPEN_R | Rule::INPUT | Rule::TRUNC_R | Rule::DUP_OUT | Rule::DUP_IN => {
+                    redirections.push(Redirection::new(p))
+                }
+                Rule::FD_REDIRECTION => {
+                    let mut inner = p.into_inner();
+                    let fd = inner.next().unwrap().as_str().parse().unwrap_or(RawFd::MAX);
+                    let r = Redirection::new(inner.next().unwrap());
+                    redirections.push(Redirection::Fd(fd, Box::new(r)));
+                }
+                Rule::assignment => {
+                    let text = p.as_str().to_owned();
+                    args.push(CmdArg::Declaration(Assignment::new(p), text))
+                }
                 Rule::HEREDOC => redirections.push(Redirection::load_heredoc(
                     p.into_inner().next().unwrap().as_str().to_owned(),
                     rl,
@@ -171,7 +216,6 @@ impl SimpleCommand {
                 r => todo!("{r:?}"),
             }
         }
-        let mut args: Vec<CmdArg> = Vec::new();
         // let mut assigns = Vec::new();
         let mut i = 0;
         while i < tokens.len() {
@@ -181,7 +225,7 @@ impl SimpleCommand {
                     args.push(CmdArg::Assignment(key.clone(), val.clone()));
                     i += 3;
                 }
-                [Token::Word(word)] => {
+                [Token::Word(word)] | [Token::SingleQuote(word)] => {
                     args.push(CmdArg::Arg(word.clone()));
                     i += 1;
                 }
@@ -192,14 +236,9 @@ impl SimpleCommand {
                 _ => break,
             }
         }
-        tokens.drain(i..).for_each(|t| match t {
-            Token::Word(s) => args.push(CmdArg::Arg(s)),
-            Token::Quote(q) => args.push(CmdArg::Quoted(q)),
-            Token::Eq => args.push(CmdArg::OpEq),
-            Token::Neq => args.push(CmdArg::OpNeq),
-            Token::VarExp(v) => args.push(CmdArg::Variable(v)),
-        });
+        args.extend(tokens.drain(i..).map(CmdArg::from));
         Ok(Self {
+            assignments,
             name,
             args,
             redirections,
@@ -215,36 +254,64 @@ pub enum CmdArg {
     /// A variable assignment like `FOO=bar`
     Assignment(String, String),
 
-    /// A quoted argument like `"foo bar"` (preserves space, no expansion yet)
+    /// An assignment given to `declare`, `export` or `local`, arrays and all,
+    /// made when the builtin runs. Kept with its text
+    Declaration(Assignment, String),
+
+    /// A quoted argument like `"foo bar"` (preserves space, `$VAR`s are expanded)
     Quoted(String),
 
     /// Logical operators used in `test` or `[` expressions
     OpEq,
     OpNeq,
 
-    /// (Optional/future) Variable expansion like `$FOO`
+    /// Variable expansion like `$FOO` or `${arr[@]}`
     Variable(String),
 
     /// (Optional/future) Command substitution like `$(ls)`
+    #[allow(dead_code)]
     CommandSub(String),
 }
+impl From<Token> for CmdArg {
+    fn from(t: Token) -> Self {
+        match t {
+            Token::Word(s) | Token::SingleQuote(s) => CmdArg::Arg(s),
+            Token::Quote(q) => CmdArg::Quoted(q),
+            Token::Eq => CmdArg::OpEq,
+            Token::Neq => CmdArg::OpNeq,
+            Token::VarExp(v) => CmdArg::Variable(v),
+        }
+    }
+}
+
 impl CmdArg {
-    pub fn as_str(&self) -> &str {
+    /// The argument's text, only allocating for an assignment
+    pub fn as_str(&self) -> Cow<'_, str> {
+        match self {
+            CmdArg::Arg(s) | CmdArg::Quoted(s) | CmdArg::Variable(s) | CmdArg::CommandSub(s) => {
+                Cow::Borrowed(s)
+            }
+            CmdArg::Assignment(l, r) => Cow::Owned(format!("{l}={r}")),
+            CmdArg::Declaration(_, text) => Cow::Borrowed(text),
+            CmdArg::OpEq => Cow::Borrowed("="),
+            CmdArg::OpNeq => Cow::Borrowed("!="),
+        }
+    }
+    pub fn into_string(self) -> String {
         match self {
-            CmdArg::Arg(s) => s,
-            CmdArg::Assignment(_, _) => todo!(),
-            CmdArg::Quoted(_) => todo!(),
-            CmdArg::OpEq => todo!(),
-            CmdArg::OpNeq => todo!(),
-            CmdArg::Variable(_) => todo!(),
-            CmdArg::CommandSub(_) => todo!(),
+            CmdArg::Arg(s) | CmdArg::Quoted(s) | CmdArg::Variable(s) | CmdArg::CommandSub(s) => s,
+            CmdArg::Assignment(l, r) => format!("{l}={r}"),
+            CmdArg::Declaration(_, text) => text,
+            CmdArg::OpEq => "=".to_owned(),
+            CmdArg::OpNeq => "!=".to_owned(),
         }
     }
     pub fn as_os_string(&self) -> OsString {
         match self {
             CmdArg::Arg(s) => std::ffi::OsString::from(s.to_string()),
-            CmdArg::Assignment(l, r) => std::ffi::OsString::from(format!("{l}=\"{r}\"")),
-            CmdArg::Quoted(q) => std::ffi::OsString::from(format!("\"{q}\"")),
+            CmdArg::Assignment(l, r) => std::ffi::OsString::from(format!("{l}={r}")),
+            CmdArg::Declaration(_, text) => std::ffi::OsString::from(text),
+            CmdArg::Quoted(q) => std::ffi::OsString::from(q),
             CmdArg::OpEq => std::ffi::OsString::from("="),
             CmdArg::OpNeq => std::ffi::OsString::from("!="),
             CmdArg::Variable(v) => std::ffi::OsString::from(v.to_string()),
@@ -258,6 +325,7 @@ impl Display for CmdArg {
         match self {
             CmdArg::Arg(s) => write!(f, "{s}"),
             CmdArg::Assignment(l, r) => write!(f, "{l}=\"{r}\""),
+            CmdArg::Declaration(_, text) => write!(f, "{text}"),
             CmdArg::Quoted(q) => write!(f, "\"{q}\""),
             CmdArg::OpEq => write!(f, "="),
             CmdArg::OpNeq => write!(f, "!="),
@@ -302,23 +370,242 @@ impl Conditional {
     }
 }
 
+/// One shell word kept as its adjacent parts, so quoted pieces can be told
+/// apart from expansions and matched literally against patterns and regexes
+pub type Word = Vec<Token>;
+
+#[derive(Debug, Clone)]
+pub enum CondExpr {
+    And(Box<Self>, Box<Self>),
+    Or(Box<Self>, Box<Self>),
+    Not(Box<Self>),
+    /// `-f file`, `-n str`, ...
+    Unary(String, Word),
+    /// `==`, `!=`, `<`, `-eq`, ...
+    Binary(Word, String, Word),
+    /// `str =~ regex`
+    Regex(Word, Word),
+    /// A lone word, true when it expands to a non-empty string
+    Word(Word),
+}
+
+impl CondExpr {
+    fn new(rule: ParsedPair<'_>) -> Self {
+        match rule.as_rule() {
+            Rule::cond_or | Rule::cond_and => {
+                let is_or = rule.as_rule() == Rule::cond_or;
+                let mut iter = rule.into_inner().map(Self::new);
+                let first = iter.next().unwrap();
+                iter.fold(first, |left, right| {
+                    if is_or {
+                        Self::Or(Box::new(left), Box::new(right))
+                    } else {
+                        Self::And(Box::new(left), Box::new(right))
+                    }
+                })
+            }
+            Rule::cond_not => {
+                let mut bangs = 0;
+                let mut expr = None;
+                for p in rule.into_inner() {
+                    match p.as_rule() {
+                        Rule::cond_bang => bangs += 1,
+                        _ => expr = Some(Self::new(p)),
+                    }
+                }
+                (0..bangs).fold(expr.unwrap(), |e, _| Self::Not(Box::new(e)))
+            }
+            Rule::cond_regex => {
+                let mut iter = rule.into_inner();
+                let left = Self::cond_word(iter.next().unwrap());
+                Self::Regex(left, Self::cond_word(iter.next().unwrap()))
+            }
+            Rule::cond_binary => {
+                let mut iter = rule.into_inner();
+                let left = Self::cond_word(iter.next().unwrap());
+                let op = iter.next().unwrap().as_str().to_owned();
+                Self::Binary(left, op, Self::cond_word(iter.next().unwrap()))
+            }
+            Rule::cond_unary => {
+                let mut iter = rule.into_inner();
+                let op = iter.next().unwrap().as_str().to_owned();
+                Self::Unary(op, Self::cond_word(iter.next().unwrap()))
+            }
+            Rule::cond_word => Self::Word(Self::cond_word(rule)),
+            r => unreachable!("{r:?}"),
+        }
+    }
+
+    fn cond_word(rule: ParsedPair<'_>) -> Word {
+        rule.into_inner()
+            .map(|p| match p.as_rule() {
+                Rule::cond_lit | Rule::cond_regex_lit => Token::Word(p.as_str().to_owned()),
+                _ => Token::new(p),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AssignValue {
+    Word(Word),
+    /// `(a b [k]=c)`, each element with its optional subscript
+    Array(Vec<(Option<String>, Word)>),
+}
+
+/// `NAME=value`, `NAME+=value` or `NAME[sub]=value`
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub name: String,
+    /// Raw subscript text, expanded when the assignment runs
+    pub index: Option<String>,
+    pub append: bool,
+    pub value: AssignValue,
+}
+
+impl Assignment {
+    fn new(rule: ParsedPair<'_>) -> Self {
+        let mut parts = rule.into_inner();
+        let (name, index) = Self::name(parts.next().unwrap());
+        let append = parts.next().unwrap().as_str() == "+=";
+        let value = match parts.next() {
+            Some(p) if p.as_rule() == Rule::array_literal => AssignValue::Array(
+                p.into_inner()
+                    .map(|elem| {
+                        let mut elem = elem.into_inner();
+                        let first = elem.next().unwrap();
+                        match first.as_rule() {
+                            Rule::subscript => (
+                                Some(Self::subscript(first)),
+                                Self::value(elem.next().unwrap()),
+                            ),
+                            _ => (None, Self::value(first)),
+                        }
+                    })
+                    .collect(),
+            ),
+            Some(p) => AssignValue::Word(Self::value(p)),
+            None => AssignValue::Word(Vec::new()),
+        };
+        Self {
+            name,
+            index,
+            append,
+            value,
+        }
+    }
+
+    /// `name=value` with the value taken as it is
+    pub fn scalar(name: &str, value: String) -> Self {
+        Self {
+            name: name.to_owned(),
+            index: None,
+            append: false,
+            value: AssignValue::Word(vec![Token::SingleQuote(value)]),
+        }
+    }
+
+    fn name(rule: ParsedPair<'_>) -> (String, Option<String>) {
+        let mut parts = rule.into_inner();
+        let name = parts.next().unwrap().as_str().to_owned();
+        (name, parts.next().map(Self::subscript))
+    }
+
+    fn subscript(rule: ParsedPair<'_>) -> String {
+        let s = rule.as_str();
+        s[1..s.len() - 1].to_owned()
+    }
+
+    fn value(rule: ParsedPair<'_>) -> Word {
+        rule.into_inner().map(Token::new).collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct WhileLoop {
     pub condition: Box<Command>,
     pub body: Box<Command>,
 }
 
+/// Which of the left command's streams feed the right side of a pipe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeKind {
+    /// `|`
+    Stdout,
+    /// `|&`, shorthand for `2>&1 |`
+    Both,
+}
+
+/// `select name [in word...]; do body; done`
+#[derive(Debug, Clone)]
+pub struct SelectLoop {
+    pub name: String,
+    /// `None` without an `in`, meaning the positional parameters
+    pub words: Option<Vec<CmdArg>>,
+    pub body: Box<Command>,
+}
+
+/// `for ((init; cond; step)); do body; done`, each part kept as raw text
+#[derive(Debug, Clone)]
+pub struct ArithFor {
+    pub init: String,
+    pub cond: String,
+    pub step: String,
+    pub body: Box<Command>,
+}
+
+/// `coproc [NAME] command`, run in the background with pipes to both ends
+#[derive(Debug, Clone)]
+pub struct Coproc {
+    pub name: String,
+    pub body: Box<Command>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Command {
     Simple(SimpleCommand),
     Conditional(Conditional),
     Sequence(Vec<Self>),
-    Pipeline(Box<Self>, Box<Self>),
+    Pipeline(Box<Self>, Box<Self>, PipeKind),
     And(Box<Self>, Box<Self>),
     Or(Box<Self>, Box<Self>),
     WhileLoop(WhileLoop),
+    SelectLoop(SelectLoop),
+    CondTest(CondExpr),
+    Coproc(Coproc),
+    Assignments(Vec<Assignment>),
+    /// `(( expr ))`
+    Arith(String),
+    /// `! pipeline`
+    Not(Box<Self>),
+    ArithFor(ArithFor),
+    /// A pipeline with its text, which is what the job table shows
+    Job(Box<Self>, String),
+    /// `list &`, run without waiting for it
+    Background(Box<Self>, String),
 }
 impl Command {
+    /// A `command_list`'s commands one by one, each with the line it starts on
+    pub fn numbered(
+        rule: ParsedPair<'_>,
+        env: (&HashMap<String, String>, &HashMap<String, String>),
+        rl: &mut Option<&mut Editor<(), FileHistory>>,
+    ) -> TrshResult<Vec<(usize, Self)>> {
+        let mut v = Vec::new();
+        let mut text = "";
+        for r in rule.into_inner() {
+            if r.as_rule() == Rule::background {
+                let (line, cmd) = v.pop().unwrap();
+                v.push((line, Self::Background(Box::new(cmd), text.to_owned())));
+                continue;
+            }
+            text = r.as_str().trim();
+            let line = r.line_col().0;
+            v.push((line, Self::new(r, env, rl)?));
+        }
+        Ok(v)
+    }
+
     pub fn new(
         rule: ParsedPair<'_>,
         env: (&HashMap<String, String>, &HashMap<String, String>),
@@ -326,13 +613,12 @@ impl Command {
     ) -> TrshResult<Self> {
         Ok(match rule.as_rule() {
             Rule::program => todo!(),
-            Rule::command_list => {
-                let mut v = Vec::new();
-                for r in rule.into_inner() {
-                    v.push(Self::new(r, env, rl)?);
-                }
-                Self::Sequence(v)
-            }
+            Rule::command_list => Self::Sequence(
+                Self::numbered(rule, env, rl)?
+                    .into_iter()
+                    .map(|(_, cmd)| cmd)
+                    .collect(),
+            ),
             Rule::if_clause => Self::Conditional(Conditional::new(rule, env, rl)?),
             Rule::simple_command => Self::Simple(SimpleCommand::new(rule, env, rl)?),
             Rule::WHITESPACE => todo!(),
@@ -340,13 +626,25 @@ impl Command {
             Rule::command => todo!(),
             Rule::command_name => todo!(),
             Rule::pipeline => {
-                let mut segments = rule.into_inner().map(|r| Command::new(r, env, rl));
-                let first = segments.next().unwrap()?;
-                segments.try_fold(first, |left, right_res| -> TrshResult<Self> {
-                    let right = right_res?;
-                    Ok(Command::Pipeline(Box::new(left), Box::new(right)))
-                })?
-                // todo!()
+                let text = rule.as_str().trim().to_owned();
+                let mut iter = rule.into_inner().peekable();
+                let negated = iter.next_if(|p| p.as_rule() == Rule::bang).is_some();
+                let mut left = Command::new(iter.next().unwrap(), env, rl)?;
+
+                while let Some(op) = iter.next() {
+                    let right = Command::new(iter.next().unwrap(), env, rl)?;
+                    let kind = match op.as_rule() {
+                        Rule::pipe => PipeKind::Stdout,
+                        Rule::pipe_and => PipeKind::Both,
+                        _ => unreachable!(),
+                    };
+                    left = Command::Pipeline(Box::new(left), Box::new(right), kind);
+                }
+                let pipeline = match negated {
+                    true => Command::Not(Box::new(left)),
+                    false => left,
+                };
+                Command::Job(Box::new(pipeline), text)
             }
             Rule::and_or => {
                 // println!("{rule:?}");
@@ -364,6 +662,64 @@ impl Command {
                 left
             }
             Rule::test_cond => Self::Simple(SimpleCommand::new(rule, env, rl)?),
+            Rule::select_loop => {
+                let mut iter = rule.into_inner().peekable();
+                let name = iter.next().unwrap().as_str().to_owned();
+                let words = iter
+                    .next_if(|p| p.as_rule() == Rule::select_words)
+                    .map(|p| {
+                        p.into_inner()
+                            .map(|w| CmdArg::from(Token::new(w)))
+                            .collect()
+                    });
+                Self::SelectLoop(SelectLoop {
+                    name,
+                    words,
+                    body: Box::new(Self::new(iter.next().unwrap(), env, rl)?),
+                })
+            }
+            Rule::coproc_command => {
+                let mut iter = rule.into_inner().peekable();
+                let name = iter
+                    .next_if(|p| p.as_rule() == Rule::var_name)
+                    .map_or("COPROC".to_owned(), |p| p.as_str().to_owned());
+                Self::Coproc(Coproc {
+                    name,
+                    body: Box::new(Self::new(iter.next().unwrap(), env, rl)?),
+                })
+            }
+            Rule::brace_group => Self::new(rule.into_inner().next().unwrap(), env, rl)?,
+            Rule::assignment_list => {
+                Self::Assignments(rule.into_inner().map(Assignment::new).collect())
+            }
+            Rule::arith_command => {
+                Self::Arith(rule.into_inner().next().unwrap().as_str().to_owned())
+            }
+            Rule::arith_for => {
+                let mut iter = rule.into_inner();
+                let mut text = || iter.next().unwrap().as_str().to_owned();
+                let (init, cond, step) = (text(), text(), text());
+                Self::ArithFor(ArithFor {
+                    init,
+                    cond,
+                    step,
+                    body: Box::new(Self::new(iter.next().unwrap(), env, rl)?),
+                })
+            }
+            Rule::let_command => Self::Simple(SimpleCommand {
+                assignments: Vec::new(),
+                name: CmdName::Builtin(Builtin::Let),
+                args: rule
+                    .into_inner()
+                    .skip(1)
+                    .map(|p| match p.as_rule() {
+                        Rule::let_lit => CmdArg::Arg(p.as_str().to_owned()),
+                        _ => CmdArg::from(Token::new(p)),
+                    })
+                    .collect(),
+                redirections: Vec::new(),
+            }),
+            Rule::cond_command => Self::CondTest(CondExpr::new(rule.into_inner().next().unwrap())),
             Rule::while_loop => {
                 let mut iter = rule.into_inner();
                 Self::WhileLoop(WhileLoop {
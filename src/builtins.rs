@@ -1,6 +1,8 @@
 use phf::phf_map;
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
+// the ones missing from BUILTINS aren't written yet
+#[allow(dead_code, clippy::enum_variant_names)]
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Builtin {
     Colon, // :
@@ -11,6 +13,7 @@ pub enum Builtin {
     Caller,
     Command,
     Declare,
+    Disown,
     Echo,
     Enable,
     Help,
@@ -56,10 +59,14 @@ pub enum Builtin {
 }
 
 pub static BUILTINS: phf::Map<&'static str, Builtin> = phf_map! {
+    "." => Builtin::Dot,
     "alias" => Builtin::Alias,
+    "builtin" => Builtin::Builtin,
     "cd" => Builtin::Cd,
     "command" => Builtin::Command,
     "continue" => Builtin::Continue,
+    "declare" => Builtin::Declare,
+    "disown" => Builtin::Disown,
     "eval" => Builtin::Eval,
     "echo" => Builtin::Echo,
     "exit" => Builtin::Exit,
@@ -76,11 +83,14 @@ pub static BUILTINS: phf::Map<&'static str, Builtin> = phf_map! {
     "hash" => Builtin::Hash,
     "jobs" => Builtin::Jobs,
     "kill" => Builtin::Kill,
+    "let" => Builtin::Let,
+    "local" => Builtin::Local,
     "read" => Builtin::Read,
     "readonly" => Builtin::Readonly,
     "return" => Builtin::Return,
     "set" => Builtin::Set,
     "shift" => Builtin::Shift,
+    "source" => Builtin::Source,
     "test" => Builtin::Test,
     "[" => Builtin::Test,
     "times" => Builtin::Times,
@@ -93,6 +103,12 @@ pub static BUILTINS: phf::Map<&'static str, Builtin> = phf_map! {
     "wait" => Builtin::Wait,
 };
 
+/// Words the grammar reserves, which `type` reports as keywords
+pub static KEYWORDS: &[&str] = &[
+    "if", "then", "else", "fi", "while", "do", "done", "for", "select", "in", "coproc", "!", "[[",
+    "]]", "{", "}",
+];
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum CmdName {
     Builtin(Builtin),
@@ -101,3 +117,31 @@ pub enum CmdName {
     Function(String),
     Unknown(String),
 }
+
+impl CmdName {
+    /// What a command's name refers to: a path if it has a `/`, then an alias,
+    /// a function, a builtin, and otherwise something to look for on PATH
+    pub fn new(
+        name: &str,
+        (aliases, functions): (&HashMap<String, String>, &HashMap<String, String>),
+    ) -> Self {
+        match (aliases.get(name), functions.get(name)) {
+            _ if name.contains('/') => Self::plain(name),
+            (Some(alias), _) => Self::Alias(alias.clone()),
+            (None, Some(func)) => Self::Function(func.clone()),
+            (None, None) => Self::plain(name),
+        }
+    }
+
+    /// What a name refers to passing over aliases and functions, as for
+    /// `command`
+    pub fn plain(name: &str) -> Self {
+        if name.contains('/') {
+            return Self::Path(PathBuf::from(name));
+        }
+        match BUILTINS.get(name) {
+            Some(builtin) => Self::Builtin(*builtin),
+            None => Self::Unknown(name.to_owned()),
+        }
+    }
+}
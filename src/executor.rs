@@ -1,31 +1,104 @@
+use builtins::BuiltinIo;
+use coproc::CoprocFds;
+use hash::HashTable;
+use jobs::{JobStart, JobTable, default_terminal_signals};
+use options::ShellOptions;
 use pest::Parser;
-use utils::{exit_zero, is_executable};
+use redirect::{Input, Streams, place_fd};
+use trap::Trap;
+pub(crate) use utils::io_error_message;
+use utils::{
+    exit_num, exit_zero, is_executable, read_line_unbuffered, signal_description, status_code,
+};
+use vars::{Var, VarValue};
+mod arith;
 mod builtins;
+mod command;
+mod cond;
+mod coproc;
+mod hash;
+mod jobs;
+mod options;
+mod pipeline;
+mod redirect;
+mod source;
+mod trap;
 mod utils;
+mod vars;
 
 use crate::{
     ExecError, Program, TrshError, TrshResult,
-    ast::{CmdArg, Command, Conditional, Redirection, SimpleCommand, WhileLoop},
-    builtins::CmdName,
+    ast::{CmdArg, Command, Conditional, SelectLoop, SimpleCommand, WhileLoop},
+    builtins::{Builtin, CmdName},
     prsr::{Rule, TrshPrsr},
 };
+use nix::{
+    errno::Errno,
+    sys::{
+        signal::{SigHandler, Signal, signal},
+        termios::Termios,
+    },
+    unistd::{ForkResult, Pid, fork},
+};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::Display,
-    io::{self, Stdout, Write},
-    path::PathBuf,
+    io::{self, Write},
+    os::{
+        fd::{AsRawFd, OwnedFd},
+        unix::process::CommandExt,
+    },
+    path::{Path, PathBuf},
     process::{ExitStatus, Stdio},
 };
 
 pub struct Executor {
-    env_vars: HashMap<String, String>,
-    vars: HashMap<String, String>,
+    /// Shell variables, the exported ones make up children's environment
+    vars: HashMap<String, Var>,
     cwd: PathBuf,
     home_dir: PathBuf,
     last_status: i32,
     aliases: HashMap<String, String>,
     functions: HashMap<String, String>,
-    std_out: Stdout,
+    /// How many loops deep the command being run is
+    loop_depth: usize,
+    /// Set by `break`/`continue` until the loop it targets sees it, and by
+    /// `return` until the sourced file is left
+    loop_ctl: Option<LoopCtl>,
+    /// How many sourced files deep the command being run is
+    sourcing: usize,
+    coprocs: HashMap<String, CoprocFds>,
+    options: ShellOptions,
+    /// Nonzero while running a condition, where `set -e` doesn't apply
+    errexit_ignored: usize,
+    jobs: JobTable,
+    /// Whether jobs get process groups of their own and the terminal
+    job_control: bool,
+    /// The job whose processes are being started
+    starting: Option<JobStart>,
+    /// The pid of the last command run with `&`, `$!`
+    last_background: Option<Pid>,
+    /// The terminal modes to restore when a foreground job is done with it
+    shell_tmodes: Option<Termios>,
+    /// `trap` actions, an empty one meaning the signal is ignored
+    traps: BTreeMap<Trap, String>,
+    /// Set while a trap's action runs
+    in_trap: bool,
+    /// `$0` followed by the arguments the shell was run with
+    positional: Vec<String>,
+    /// Where commands were found on PATH
+    hashed: HashTable,
+}
+
+/// Where commands are looked for when PATH isn't set, and by `command -p`
+const DEFAULT_PATH: &str = "/usr/bin:/bin";
+
+#[derive(Debug, Clone, Copy)]
+pub enum LoopCtl {
+    Break(usize),
+    Continue(usize),
+    /// Leaving a sourced file with the status
+    Return(i32),
 }
 impl Display for Executor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -47,18 +120,37 @@ impl Executor {
     pub fn new() -> Self {
         let cwd = std::env::current_dir().unwrap();
         let home_dir = dirs::home_dir().unwrap();
-        let std_out = io::stdout();
-        let env_vars = std::env::vars().collect();
-        let vars = HashMap::new();
+        let vars = std::env::vars()
+            .map(|(name, value)| {
+                let var = Var {
+                    value: VarValue::Scalar(value),
+                    exported: true,
+                };
+                (name, var)
+            })
+            .collect();
         Self {
-            env_vars,
             vars,
             cwd,
             home_dir,
             last_status: 0,
             aliases: HashMap::new(),
             functions: HashMap::new(),
-            std_out,
+            loop_depth: 0,
+            loop_ctl: None,
+            sourcing: 0,
+            coprocs: HashMap::new(),
+            options: ShellOptions::default(),
+            errexit_ignored: 0,
+            jobs: JobTable::default(),
+            job_control: false,
+            starting: None,
+            last_background: None,
+            shell_tmodes: None,
+            traps: BTreeMap::new(),
+            in_trap: false,
+            positional: vec!["trsh".to_owned()],
+            hashed: HashTable::default(),
         }
     }
     pub fn load_trshrc(&mut self) {
@@ -74,9 +166,9 @@ impl Executor {
                             .and_then(|mut r| {
                                 Program::new(r.next().unwrap(), self.env(), &mut None)
                             })
-                            .and_then(|prog| self.exec(prog.0, None, None))
+                            .and_then(|prog| self.exec(prog.0, None, None, None))
                             .map(|_| {})
-                            .map_err(|e| eprintln!("{e:?}"))
+                            .map_err(|e| eprintln!("trsh: {e}"))
                             .ok();
                     }
                 }
@@ -85,72 +177,245 @@ impl Executor {
         }
     }
 
+    /// Parses and runs a string of commands
+    pub fn exec_str(&mut self, s: &str) -> TrshResult<ExitStatus> {
+        let mut pairs =
+            TrshPrsr::parse(Rule::program, s).map_err(|e| TrshError::Pest(Box::new(e)))?;
+        let prog = Program::new(pairs.next().unwrap(), self.env(), &mut None)?;
+        self.exec(prog.0, None, None, None)
+    }
+
     pub fn exec(
         &mut self,
         cmd: Command,
-        stdin: Option<Stdio>,
-        stdout: Option<Stdio>,
+        stdin: Option<OwnedFd>,
+        stdout: Option<OwnedFd>,
+        stderr: Option<OwnedFd>,
     ) -> TrshResult<ExitStatus> {
-        match cmd {
-            Command::Simple(simple_command) => self.exec_simple(simple_command, stdin, stdout),
-            Command::Conditional(conditional) => self.exec_conditional(conditional, stdin, stdout),
-            Command::Sequence(commands) => {
-                let r = commands
-                    .into_iter()
-                    .try_for_each(|c| self.exec(c, None, None).map(|_| ()));
-                r.map(|_| exit_zero())
+        let leaf = matches!(
+            cmd,
+            Command::Simple(_) | Command::Pipeline(..) | Command::Arith(_) | Command::CondTest(_)
+        );
+        if matches!(
+            cmd,
+            Command::Simple(_) | Command::Arith(_) | Command::CondTest(_)
+        ) {
+            self.run_trap(Trap::Debug);
+        }
+        let status = match cmd {
+            Command::Simple(simple_command) => {
+                let status = self.exec_simple(simple_command, stdin, stdout, stderr);
+                let status = self.command_status(status)?;
+                self.set_pipestatus(&[status]);
+                Ok(status)
             }
-            Command::Pipeline(left, right) => {
-                let (in_pipe, out_pipe) = os_pipe::pipe().unwrap();
-                self.exec(*left, None, Some(out_pipe.into()))?;
-                self.exec(*right, Some(in_pipe.into()), None)
-                // Ok(())
+            Command::Not(cmd) => match self
+                .ignoring_errexit(|this| this.exec(*cmd, stdin, stdout, stderr))?
+                .success()
+            {
+                true => Ok(exit_num(1)),
+                false => Ok(exit_zero()),
+            },
+            Command::Conditional(conditional) => self.exec_conditional(conditional),
+            Command::Sequence(commands) => {
+                let mut status = exit_zero();
+                for c in commands {
+                    if self.loop_ctl.is_some() {
+                        break;
+                    }
+                    status = self.exec(c, None, None, None)?;
+                }
+                Ok(status)
             }
+            pipeline @ Command::Pipeline(..) => self.exec_pipeline(pipeline, stdin, stdout, stderr),
             Command::And(left, right) => {
-                let left_status = self.exec(*left, None, None)?;
+                let left_status =
+                    self.ignoring_errexit(|this| this.exec(*left, None, None, None))?;
                 if left_status.success() {
-                    self.exec(*right, None, None)
+                    self.exec(*right, None, None, None)
                 } else {
                     Ok(left_status)
                 }
             }
             Command::Or(left, right) => {
-                let left_status = self.exec(*left, None, None)?;
+                let left_status =
+                    self.ignoring_errexit(|this| this.exec(*left, None, None, None))?;
                 if !left_status.success() {
-                    self.exec(*right, None, None)
+                    self.exec(*right, None, None, None)
                 } else {
                     Ok(left_status)
                 }
             }
-            Command::WhileLoop(WhileLoop { condition, body }) => {
-                while self
-                    .exec(*condition.clone(), None, None)
+            Command::WhileLoop(WhileLoop { condition, body }) => self.in_loop(|this| {
+                while this
+                    .ignoring_errexit(|this| this.exec(*condition.clone(), None, None, None))
                     .is_ok_and(|tf| tf.success())
                 {
-                    self.exec(*body.clone(), None, None)?;
+                    this.exec(*body.clone(), None, None, None)?;
+                    if this.loop_should_exit() {
+                        break;
+                    }
                 }
                 Ok(exit_zero())
                 // let r = self.exec(*condition, None, None);
                 // println!("{r:?}");
+            }),
+            Command::SelectLoop(select) => self.in_loop(|this| this.exec_select(select)),
+            Command::ArithFor(arith_for) => self.in_loop(|this| this.exec_arith_for(arith_for)),
+            Command::Arith(expr) => self.exec_arith(&expr),
+            Command::CondTest(expr) => self.exec_cond_test(&expr),
+            Command::Coproc(coproc) => self.exec_coproc(coproc),
+            Command::Job(cmd, text) => self.exec_job(*cmd, text, stdin, stdout, stderr),
+            Command::Background(cmd, text) => self.exec_background(*cmd, text),
+            Command::Assignments(assignments) => {
+                for a in assignments {
+                    self.assign(a)?;
+                }
+                Ok(exit_zero())
+            }
+        };
+        let status = self.command_status(status)?;
+        self.last_status = status_code(status);
+        if leaf && !status.success() && self.errexit_ignored == 0 {
+            self.run_trap(Trap::Err);
+            if self.options.errexit {
+                self.exit(self.last_status);
             }
         }
+        self.reap_coprocs();
+        self.run_pending_traps();
+        Ok(status)
     }
-    fn exec_conditional(
-        &mut self,
-        cond: Conditional,
-        stdin: Option<Stdio>,
-        stdout: Option<Stdio>,
-    ) -> TrshResult<ExitStatus> {
+
+    /// Reports a command's error and turns it into the command's status, so
+    /// the commands after it still run. Errors that abandon the whole command
+    /// line are passed on
+    fn command_status(&mut self, r: TrshResult<ExitStatus>) -> TrshResult<ExitStatus> {
+        let (msg, code) = match r {
+            Err(TrshError::Exec(ExecError::General(expl))) => (expl.to_string(), 1),
+            Err(TrshError::Exec(ExecError::Usage(expl))) => (expl.to_string(), 2),
+            Err(TrshError::Exec(ExecError::IO(e))) => (io_error_message(&e), 1),
+            Err(TrshError::Exec(ExecError::Unbound(name))) => {
+                eprintln!("trsh: {name}: unbound variable");
+                // only the interactive shell has job control, and it carries on
+                if !self.job_control {
+                    self.exit(1);
+                }
+                return Ok(exit_num(1));
+            }
+            r => return r,
+        };
+        eprintln!("trsh: {msg}");
+        Ok(exit_num(code))
+    }
+
+    /// The status of the last command, `$?`
+    pub fn last_status(&self) -> i32 {
+        self.last_status
+    }
+
+    /// Leaves the shell with `code`
+    pub fn exit(&mut self, code: i32) -> ! {
+        // taken out first, so an `exit` in the trap doesn't run it again
+        if let Some(action) = self.traps.remove(&Trap::Exit) {
+            self.run_action(&action);
+        }
+        let _ = io::stdout().flush();
+        std::process::exit(code)
+    }
+
+    /// Runs `f` as a condition, whose failure doesn't trigger `set -e`
+    fn ignoring_errexit<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.errexit_ignored += 1;
+        let r = f(self);
+        self.errexit_ignored -= 1;
+        r
+    }
+
+    fn in_loop<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.loop_depth += 1;
+        let r = f(self);
+        self.loop_depth -= 1;
+        r
+    }
+
+    /// Called by a loop after each pass of its body, true when a `break` or
+    /// `continue` means it has to stop
+    fn loop_should_exit(&mut self) -> bool {
+        match self.loop_ctl.take() {
+            Some(LoopCtl::Break(n)) => {
+                if n > 1 {
+                    self.loop_ctl = Some(LoopCtl::Break(n - 1));
+                }
+                true
+            }
+            Some(LoopCtl::Continue(n)) if n > 1 => {
+                self.loop_ctl = Some(LoopCtl::Continue(n - 1));
+                true
+            }
+            Some(LoopCtl::Continue(_)) | None => false,
+            // every loop in the sourced file stops
+            ctl @ Some(LoopCtl::Return(_)) => {
+                self.loop_ctl = ctl;
+                true
+            }
+        }
+    }
+
+    /// Prints the menu to stderr and runs the body once per line read, until
+    /// a `break` or EOF
+    fn exec_select(&mut self, select: SelectLoop) -> TrshResult<ExitStatus> {
+        let SelectLoop { name, words, body } = select;
+        let items: Vec<String> = self
+            .expand_args(words.unwrap_or_default())?
+            .into_iter()
+            .map(CmdArg::into_string)
+            .collect();
+        let mut status = exit_zero();
+        let mut show_menu = true;
+        loop {
+            if show_menu {
+                for (i, item) in items.iter().enumerate() {
+                    eprintln!("{}) {item}", i + 1);
+                }
+            }
+            eprint!("{}", self.var("PS3").unwrap_or("#? "));
+            let Some(line) = read_line_unbuffered()? else {
+                eprintln!();
+                return Ok(status);
+            };
+            let line = line.trim_end_matches('\r');
+            show_menu = line.is_empty();
+            if line.is_empty() {
+                continue;
+            }
+            let choice = line
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| items.get(n.checked_sub(1)?))
+                .cloned()
+                .unwrap_or_default();
+            self.set_var("REPLY", line.to_owned())?;
+            self.set_var(&name, choice)?;
+            status = self.exec(*body.clone(), None, None, None)?;
+            if self.loop_should_exit() {
+                return Ok(status);
+            }
+        }
+    }
+
+    fn exec_conditional(&mut self, cond: Conditional) -> TrshResult<ExitStatus> {
         let Conditional {
             condition,
             then_branch,
             else_branch,
         } = cond;
-        let status = self.exec(*condition, None, None)?;
+        let status = self.ignoring_errexit(|this| this.exec(*condition, None, None, None))?;
         if status.success() {
-            self.exec(*then_branch, None, None)
+            self.exec(*then_branch, None, None, None)
         } else if let Some(eb) = else_branch {
-            self.exec(*eb, None, None)
+            self.exec(*eb, None, None, None)
         } else {
             Ok(status)
         }
@@ -160,93 +425,304 @@ impl Executor {
     fn exec_simple(
         &mut self,
         simple_command: SimpleCommand,
-        stdin: Option<Stdio>,
-        stdout: Option<Stdio>,
+        stdin: Option<OwnedFd>,
+        stdout: Option<OwnedFd>,
+        stderr: Option<OwnedFd>,
     ) -> TrshResult<ExitStatus> {
         let SimpleCommand {
+            assignments,
             name,
             args,
             redirections,
         } = simple_command;
+        let temp_env = self.temp_assignments(assignments)?;
+        let args = self.expand_args(args)?;
+        let streams = self.redirect(redirections, stdin, stdout, stderr)?;
+        self.with_temp_vars(temp_env, |this| this.exec_cmd_name(name, args, streams))
+    }
+
+    fn exec_cmd_name(
+        &mut self,
+        name: CmdName,
+        args: Vec<CmdArg>,
+        streams: Streams,
+    ) -> TrshResult<ExitStatus> {
+        match name {
+            CmdName::Unknown(unknown_cmd) => self.exec_unknown(unknown_cmd, args, streams),
+            CmdName::Path(path) => self.exec_path(path, args, streams),
+            name => self.exec_named(name, args, streams),
+        }
+    }
+
+    /// Runs anything that stays inside the shell: builtins, aliases and functions
+    fn exec_named(
+        &mut self,
+        name: CmdName,
+        args: Vec<CmdArg>,
+        streams: Streams,
+    ) -> TrshResult<ExitStatus> {
         match name {
-            CmdName::Builtin(builtin) => self.exec_builtin(builtin, args, stdin, stdout),
-            CmdName::Unknown(unknown_cmd) => {
-                self.exec_unknown(unknown_cmd, args, redirections, stdin, stdout)
+            CmdName::Builtin(Builtin::Exec) => self.exec_exec(args, streams),
+            CmdName::Builtin(Builtin::Command) => self.exec_command(args, streams),
+            CmdName::Builtin(Builtin::Builtin) => self.exec_builtin_cmd(args, streams),
+            CmdName::Builtin(Builtin::Eval) => self.exec_eval(args, streams),
+            CmdName::Builtin(Builtin::Dot) => {
+                self.with_streams(streams, |this| this.exec_source(".", args))
+            }
+            CmdName::Builtin(Builtin::Source) => {
+                self.with_streams(streams, |this| this.exec_source("source", args))
+            }
+            CmdName::Builtin(builtin) => {
+                let mut io = BuiltinIo::from(streams);
+                let status = self.exec_builtin(builtin, args, &mut io);
+                io.stdout.flush()?;
+                io.stderr.flush()?;
+                status
             }
-            CmdName::Path(_path_buf) => todo!(),
+            CmdName::Unknown(_) | CmdName::Path(_) => unreachable!(),
             CmdName::Alias(a) => {
                 println!("{a}");
                 TrshPrsr::parse(Rule::program, &a)
                     .map_err(|e| TrshError::Pest(Box::new(e)))
                     .and_then(|mut r| Program::new(r.next().unwrap(), self.env(), &mut None))
-                    .and_then(|prog| self.exec(prog.0, stdin, stdout))
+                    .and_then(|prog| {
+                        let stdin = match streams.stdin {
+                            Input::Fd(fd) => Some(fd),
+                            Input::Inherit | Input::Text(_) => None,
+                        };
+                        self.exec(prog.0, stdin, streams.stdout, streams.stderr)
+                    })
             }
             CmdName::Function(_) => todo!(),
         }
     }
     fn exec_unknown(
-        &self,
+        &mut self,
         unknown: String,
         args: Vec<CmdArg>,
-        redirs: Vec<Redirection>,
-        stdin: Option<Stdio>,
-        stdout: Option<Stdio>,
+        streams: Streams,
     ) -> TrshResult<ExitStatus> {
-        match self.lookup_command(&unknown) {
-            Some(p) => {
-                let mut process = std::process::Command::new(p);
-                process.args(args.iter().map(CmdArg::as_os_string));
-                process.current_dir(&self.cwd);
-                for d in redirs {
-                    match d {
-                        Redirection::AppendRight(s) => {
-                            let f = std::fs::OpenOptions::new()
-                                .create(true)
-                                .append(true)
-                                .open(s)?;
-                            if stdout.is_none() {
-                                process.stdout(Stdio::from(f));
-                            }
-                        }
-                        Redirection::Input(s) => {
-                            let file = std::fs::File::open(s)?;
-                            if stdin.is_none() {
-                                process.stdin(Stdio::from(file));
-                            }
-                        }
-                        Redirection::TruncRight(s) => {
-                            let file = std::fs::File::create(s)?;
-                            if stdout.is_none() {
-                                process.stdout(Stdio::from(file));
-                            }
-                        }
-                        Redirection::HereDoc(s) => {
-                            let mut child_proc = process.stdin(Stdio::piped()).spawn()?;
-                            if let Some(mut stdin) = child_proc.stdin.take() {
-                                stdin.write_all(s.as_bytes())?;
-                            }
-                            child_proc.wait()?;
-                        }
+        match self.hashed_command(&unknown) {
+            Some(p) => self.exec_program(unknown, p, args, streams),
+            None => {
+                eprintln!("trsh: {unknown}: command not found");
+                Ok(exit_num(127))
+            }
+        }
+    }
+
+    /// Runs a command named by its path, taken from the shell's cwd
+    fn exec_path(
+        &mut self,
+        path: PathBuf,
+        args: Vec<CmdArg>,
+        streams: Streams,
+    ) -> TrshResult<ExitStatus> {
+        let name = path.display().to_string();
+        let full = self.cwd.join(&path);
+        if full.is_dir() {
+            eprintln!("trsh: {name}: Is a directory");
+            return Ok(exit_num(126));
+        }
+        self.exec_program(name, full, args, streams)
+    }
+
+    /// Spawns the program at `path` and waits for it, `name` being what it
+    /// was called as
+    pub fn exec_program(
+        &mut self,
+        name: String,
+        path: PathBuf,
+        args: Vec<CmdArg>,
+        streams: Streams,
+    ) -> TrshResult<ExitStatus> {
+        if self.job_control && self.starting.is_none() {
+            // part of a compound command, the command is a job on its own
+            let text = std::iter::once(name.clone())
+                .chain(args.iter().map(CmdArg::to_string))
+                .collect::<Vec<_>>()
+                .join(" ");
+            return self.foreground_job(text, |this| this.exec_program(name, path, args, streams));
+        }
+        let mut process = std::process::Command::new(&path);
+        process.args(args.iter().map(CmdArg::as_os_string));
+        process.env_clear().envs(self.exported());
+        process.current_dir(&self.cwd);
+        // the child gets copies, the streams are still needed if the file
+        // turns out to be a script
+        match &streams.stdin {
+            Input::Inherit => (),
+            Input::Fd(fd) => {
+                process.stdin(Stdio::from(fd.try_clone()?));
+            }
+            Input::Text(_) => {
+                process.stdin(Stdio::piped());
+            }
+        };
+        if let Some(s_out) = &streams.stdout {
+            process.stdout(Stdio::from(s_out.try_clone()?));
+        }
+        if let Some(s_err) = &streams.stderr {
+            process.stderr(Stdio::from(s_err.try_clone()?));
+        }
+        if !streams.extra.is_empty() {
+            let extra = streams
+                .extra
+                .iter()
+                .map(|(n, fd)| Ok((*n, fd.try_clone()?)))
+                .collect::<io::Result<Vec<_>>>()?;
+            // SAFETY: only makes syscalls, between fork and exec
+            unsafe {
+                process.pre_exec(move || {
+                    for (n, fd) in &extra {
+                        place_fd(fd.as_raw_fd(), *n)?;
                     }
+                    Ok(())
+                })
+            };
+        }
+        if let Some(setup) = self.child_setup() {
+            // SAFETY: only makes syscalls, between fork and exec
+            unsafe {
+                process.pre_exec(move || {
+                    setup.apply();
+                    Ok(())
+                })
+            };
+        }
+        let mut child = match process.spawn() {
+            Ok(child) => child,
+            Err(e) if e.raw_os_error() == Some(Errno::ENOEXEC as i32) => {
+                let positional = std::iter::once(name)
+                    .chain(args.into_iter().map(CmdArg::into_string))
+                    .collect();
+                return self.exec_script(&path, positional, streams);
+            }
+            Err(e) => {
+                let code = match e.kind() {
+                    io::ErrorKind::NotFound => 127,
+                    _ => 126,
+                };
+                eprintln!("trsh: {name}: {}", io_error_message(&e));
+                return Ok(exit_num(code));
+            }
+        };
+        if let (Input::Text(s), Some(mut stdin)) = (&streams.stdin, child.stdin.take()) {
+            // the command may well exit without reading all of it
+            let _ = stdin.write_all(s.as_bytes());
+        }
+        let pid = Pid::from_raw(child.id() as i32);
+        self.joined_job(pid);
+        let status = self.wait_pid(pid)?;
+        if let Some(description) = signal_description(status) {
+            eprintln!("{description}");
+        }
+        Ok(status)
+    }
+
+    /// Runs a file with no `#!` line, which the kernel won't exec, as a trsh
+    /// script in a child with a shell of its own
+    fn exec_script(
+        &mut self,
+        path: &Path,
+        positional: Vec<String>,
+        streams: Streams,
+    ) -> TrshResult<ExitStatus> {
+        let script = match std::fs::read_to_string(path) {
+            Ok(script) => script,
+            Err(e) => {
+                eprintln!("trsh: {}: {}", positional[0], io_error_message(&e));
+                return Ok(exit_num(126));
+            }
+        };
+        io::stdout().flush()?;
+        // SAFETY: the shell is single threaded, and the child only builds a
+        // new executor before running the script
+        match unsafe { fork() }.map_err(io::Error::from)? {
+            ForkResult::Child => {
+                // SAFETY: restoring the default handler, as an exec would
+                let _ = unsafe { signal(Signal::SIGPIPE, SigHandler::SigDfl) };
+                if let Some(setup) = self.child_setup() {
+                    setup.apply();
                 }
-                if let Some(s_in) = stdin {
-                    process.stdin(s_in);
+                if let Err(e) = streams.install() {
+                    eprintln!("trsh: {}: {}", positional[0], io_error_message(&e));
+                    std::process::exit(126);
                 }
-                if let Some(s_out) = stdout {
-                    process.stdout(s_out);
-                } else {
-                    process.stdout(Stdio::inherit());
+                let mut shell = Executor::new();
+                shell.cwd = self.cwd.clone();
+                shell.vars = self
+                    .exported()
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let var = Var {
+                            value: VarValue::Scalar(value.to_owned()),
+                            exported: true,
+                        };
+                        (name.to_owned(), var)
+                    })
+                    .collect();
+                shell.positional = positional;
+                if let Err(e) = shell.exec_str(&script) {
+                    eprintln!("trsh: {e}");
                 }
-                Ok(process.status()?)
-                // match process.status() {
-                //     Ok(_) => (),
-                //     Err(e) => eprintln!("trsh: {}: exec error", e),
-                // }
+                shell.exit(shell.last_status)
+            }
+            ForkResult::Parent { child } => {
+                self.joined_job(child);
+                self.wait_pid(child)
             }
-            None => Err(TrshError::Exec(ExecError::UnknownCmd)),
         }
-        //Ok(())
     }
+
+    /// `exec [cmd [args...]]`: replaces the shell with `cmd`, or with no
+    /// command keeps the redirections for the rest of the shell's life
+    fn exec_exec(&mut self, args: Vec<CmdArg>, streams: Streams) -> TrshResult<ExitStatus> {
+        let Some(name) = args.first().map(CmdArg::to_string) else {
+            io::stdout().flush()?;
+            streams
+                .install()
+                .map_err(|e| TrshError::gen_exec("exec", &io_error_message(&e)))?;
+            return Ok(exit_zero());
+        };
+        let Some(path) = self.hashed_command(&name) else {
+            eprintln!("trsh: exec: {name}: not found");
+            return Ok(exit_num(127));
+        };
+        let mut process = std::process::Command::new(path);
+        process.args(args[1..].iter().map(CmdArg::as_os_string));
+        process.env_clear().envs(self.exported());
+        process.current_dir(&self.cwd);
+        if let Some(fd) = streams.stdin.into_fd()? {
+            process.stdin(Stdio::from(fd));
+        }
+        if let Some(s_out) = streams.stdout {
+            process.stdout(Stdio::from(s_out));
+        }
+        if let Some(s_err) = streams.stderr {
+            process.stderr(Stdio::from(s_err));
+        }
+        let extra = streams.extra;
+        let job_control = self.job_control;
+        // SAFETY: only makes syscalls, right before the exec
+        unsafe {
+            process.pre_exec(move || {
+                for (n, fd) in &extra {
+                    place_fd(fd.as_raw_fd(), *n)?;
+                }
+                if job_control {
+                    default_terminal_signals();
+                }
+                Ok(())
+            })
+        };
+        io::stdout().flush()?;
+        // only comes back if the exec failed
+        let e = process.exec();
+        eprintln!("trsh: exec: {name}: {}", io_error_message(&e));
+        Ok(exit_num(126))
+    }
+
     fn exec_cd(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
         if args.is_empty() {
             self.cwd = self.home_dir.clone();
@@ -261,35 +737,37 @@ impl Executor {
                     self.cwd = o;
                     Ok(exit_zero())
                 }
-                Err(e) => Err(TrshError::gen_exec("cd", &format!("{e}"))),
+                Err(e) => Err(TrshError::gen_exec(
+                    "cd",
+                    &format!("{}: {}", args[0], io_error_message(&e)),
+                )),
             }
         } else {
             Err(TrshError::gen_exec("cd", "too many arguments"))
         }
     }
 
+    /// Searches PATH for a command, or checks the file when it's a path
     fn lookup_command(&self, cmd_name: &str) -> Option<PathBuf> {
-        if cmd_name.contains('/') {
-            let path = PathBuf::from(cmd_name);
-            if path.is_file() && is_executable(&path) {
-                return Some(path);
-            } else {
-                return None;
-            }
-        }
-
-        let paths = self
-            .env_vars
-            .get("PATH")
-            .cloned()
-            .unwrap_or("/usr/bin:/bin".to_string());
-        for dir in paths.split(':') {
-            let candidate = PathBuf::from(dir).join(cmd_name);
-            if candidate.is_file() && is_executable(&candidate) {
-                return Some(candidate);
-            }
-        }
+        let paths = self.var("PATH").unwrap_or(DEFAULT_PATH);
+        self.command_paths(cmd_name, paths).next()
+    }
 
-        None
+    /// Every executable a command could be in the `:` separated `paths`, in
+    /// order, or just the file when it's a path
+    fn command_paths<'a>(
+        &'a self,
+        cmd_name: &'a str,
+        paths: &'a str,
+    ) -> impl Iterator<Item = PathBuf> + 'a {
+        let is_path = cmd_name.contains('/');
+        let path = is_path.then(|| self.cwd.join(cmd_name));
+        let candidates = paths
+            .split(':')
+            .filter(move |_| !is_path)
+            .map(move |dir| PathBuf::from(dir).join(cmd_name));
+        path.into_iter()
+            .chain(candidates)
+            .filter(|candidate| candidate.is_file() && is_executable(candidate))
     }
 }
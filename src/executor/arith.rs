@@ -0,0 +1,519 @@
+use std::process::ExitStatus;
+
+use crate::{
+    TrshError, TrshResult,
+    ast::{ArithFor, AssignValue, Assignment, CmdArg, Token},
+};
+
+use super::{
+    Executor, VarValue,
+    utils::{exit_num, exit_zero},
+};
+
+/// How deep variables holding expressions may refer to each other
+const MAX_DEPTH: usize = 64;
+
+/// Longest first, so `<<=` isn't read as `<<` then `=`
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "&=", "^=", "|=", "+", "-", "*", "/", "%", "<", ">", "&", "|", "^", "!", "~",
+    "?", ":", "=", ",", "(", ")",
+];
+
+const ASSIGN_OPS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "^=", "|=",
+];
+
+/// Left associative binary operators, loosest binding first
+const BINARY_LEVELS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", ">", "<=", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum ArithToken {
+    Num(i64),
+    /// A variable, with the raw text of its subscript if it has one
+    Name(String, Option<String>),
+    Op(&'static str),
+}
+
+#[derive(Debug)]
+enum ArithExpr {
+    Num(i64),
+    Var(String, Option<String>),
+    Unary(&'static str, Box<Self>),
+    Binary(Box<Self>, &'static str, Box<Self>),
+    Ternary(Box<Self>, Box<Self>, Box<Self>),
+    /// `name op= value`, the operator is `=` for a plain assignment
+    Assign(String, Option<String>, &'static str, Box<Self>),
+    /// `++name`, `name--`, ...
+    Step {
+        name: String,
+        sub: Option<String>,
+        delta: i64,
+        post: bool,
+    },
+}
+
+fn syntax_error(expr: &str, token: &str) -> TrshError {
+    TrshError::gen_exec(
+        expr,
+        &format!("syntax error in expression (error token is \"{token}\")"),
+    )
+}
+
+/// Reads an integer constant: decimal, `0x` hex, `0` octal or `base#digits`
+fn parse_number(expr: &str, s: &str) -> TrshResult<i64> {
+    let invalid = || TrshError::gen_exec(expr, &format!("{s}: value too great for base"));
+    let (base, digits) = match s.split_once('#') {
+        Some((base, digits)) => match base.parse::<u32>() {
+            Ok(base @ 2..=36) => (base, digits),
+            _ => return Err(TrshError::gen_exec(expr, &format!("{base}: invalid base"))),
+        },
+        None => match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => (16, hex),
+            None if s.len() > 1 && s.starts_with('0') => (8, &s[1..]),
+            None => (10, s),
+        },
+    };
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+    digits.chars().try_fold(0i64, |n, c| {
+        let d = c.to_digit(base).ok_or_else(invalid)?;
+        Ok(n.wrapping_mul(base as i64).wrapping_add(d as i64))
+    })
+}
+
+fn tokenize(expr: &str) -> TrshResult<Vec<ArithToken>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap();
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#' || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(ArithToken::Num(parse_number(expr, &rest[..len])?));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let name = rest[..len].to_owned();
+            let mut sub = None;
+            if rest[len..].starts_with('[') {
+                let mut depth = 0;
+                let end = rest[len..]
+                    .char_indices()
+                    .find(|&(_, c)| {
+                        match c {
+                            '[' => depth += 1,
+                            ']' => depth -= 1,
+                            _ => (),
+                        }
+                        depth == 0
+                    })
+                    .map(|(i, _)| len + i)
+                    .ok_or_else(|| syntax_error(expr, &rest[len..]))?;
+                sub = Some(rest[len + 1..end].to_owned());
+                len = end + 1;
+            }
+            tokens.push(ArithToken::Name(name, sub));
+            len
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| syntax_error(expr, rest))?;
+            tokens.push(ArithToken::Op(op));
+            op.len()
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens, one method per precedence level
+struct ArithParser<'a> {
+    expr: &'a str,
+    tokens: Vec<ArithToken>,
+    pos: usize,
+}
+
+impl ArithParser<'_> {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(ArithToken::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        let found = self.peek_op() == Some(op);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn error(&self) -> TrshError {
+        let token = match self.tokens.get(self.pos) {
+            Some(ArithToken::Num(n)) => n.to_string(),
+            Some(ArithToken::Name(name, _)) => name.clone(),
+            Some(ArithToken::Op(op)) => op.to_string(),
+            None => return TrshError::gen_exec(self.expr, "operand expected"),
+        };
+        syntax_error(self.expr, &token)
+    }
+
+    fn parse(mut self) -> TrshResult<ArithExpr> {
+        let expr = self.comma()?;
+        match self.pos < self.tokens.len() {
+            true => Err(self.error()),
+            false => Ok(expr),
+        }
+    }
+
+    fn comma(&mut self) -> TrshResult<ArithExpr> {
+        let mut left = self.assign()?;
+        while self.eat(",") {
+            left = ArithExpr::Binary(Box::new(left), ",", Box::new(self.assign()?));
+        }
+        Ok(left)
+    }
+
+    fn assign(&mut self) -> TrshResult<ArithExpr> {
+        let left = self.ternary()?;
+        let Some(op) = self.peek_op().filter(|op| ASSIGN_OPS.contains(op)) else {
+            return Ok(left);
+        };
+        match left {
+            ArithExpr::Var(name, sub) => {
+                self.pos += 1;
+                Ok(ArithExpr::Assign(name, sub, op, Box::new(self.assign()?)))
+            }
+            _ => Err(TrshError::gen_exec(
+                self.expr,
+                &format!("attempted assignment to non-variable (error token is \"{op}\")"),
+            )),
+        }
+    }
+
+    fn ternary(&mut self) -> TrshResult<ArithExpr> {
+        let cond = self.binary(0)?;
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+        let then = self.comma()?;
+        if !self.eat(":") {
+            return Err(TrshError::gen_exec(
+                self.expr,
+                "`:' expected for conditional expression",
+            ));
+        }
+        let otherwise = self.ternary()?;
+        Ok(ArithExpr::Ternary(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn binary(&mut self, level: usize) -> TrshResult<ArithExpr> {
+        let Some(ops) = BINARY_LEVELS.get(level) else {
+            return self.power();
+        };
+        let mut left = self.binary(level + 1)?;
+        while let Some(op) = self.peek_op().filter(|op| ops.contains(op)) {
+            self.pos += 1;
+            left = ArithExpr::Binary(Box::new(left), op, Box::new(self.binary(level + 1)?));
+        }
+        Ok(left)
+    }
+
+    /// `**` is right associative
+    fn power(&mut self) -> TrshResult<ArithExpr> {
+        let base = self.unary()?;
+        if self.eat("**") {
+            return Ok(ArithExpr::Binary(
+                Box::new(base),
+                "**",
+                Box::new(self.power()?),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn unary(&mut self) -> TrshResult<ArithExpr> {
+        match self.peek_op() {
+            Some(op @ ("!" | "~" | "-" | "+")) => {
+                self.pos += 1;
+                Ok(ArithExpr::Unary(op, Box::new(self.unary()?)))
+            }
+            Some(op @ ("++" | "--")) => {
+                self.pos += 1;
+                match self.unary()? {
+                    ArithExpr::Var(name, sub) => Ok(ArithExpr::Step {
+                        name,
+                        sub,
+                        delta: if op == "++" { 1 } else { -1 },
+                        post: false,
+                    }),
+                    _ => Err(syntax_error(self.expr, op)),
+                }
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> TrshResult<ArithExpr> {
+        let expr = self.primary()?;
+        match (expr, self.peek_op()) {
+            (ArithExpr::Var(name, sub), Some(op @ ("++" | "--"))) => {
+                self.pos += 1;
+                Ok(ArithExpr::Step {
+                    name,
+                    sub,
+                    delta: if op == "++" { 1 } else { -1 },
+                    post: true,
+                })
+            }
+            (expr, _) => Ok(expr),
+        }
+    }
+
+    fn primary(&mut self) -> TrshResult<ArithExpr> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(ArithToken::Num(n)) => {
+                self.pos += 1;
+                Ok(ArithExpr::Num(n))
+            }
+            Some(ArithToken::Name(name, sub)) => {
+                self.pos += 1;
+                Ok(ArithExpr::Var(name, sub))
+            }
+            Some(ArithToken::Op("(")) => {
+                self.pos += 1;
+                let expr = self.comma()?;
+                match self.eat(")") {
+                    true => Ok(expr),
+                    false => Err(self.error()),
+                }
+            }
+            _ => Err(self.error()),
+        }
+    }
+}
+
+impl Executor {
+    /// `(( expr ))`, true when the expression is non-zero
+    pub fn exec_arith(&mut self, expr: &str) -> TrshResult<ExitStatus> {
+        match self.eval_arith(expr)? {
+            0 => Ok(exit_num(1)),
+            _ => Ok(exit_zero()),
+        }
+    }
+
+    /// `let expr...`, the status comes from the last expression
+    pub fn exec_let(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
+        if args.is_empty() {
+            return Err(TrshError::gen_exec("let", "expression expected"));
+        }
+        let mut status = exit_zero();
+        for arg in self.expand_args(args)? {
+            status = self.exec_arith(&arg.into_string())?;
+        }
+        Ok(status)
+    }
+
+    /// `for ((init; cond; step)); do body; done`, an empty condition is true
+    pub fn exec_arith_for(&mut self, arith_for: ArithFor) -> TrshResult<ExitStatus> {
+        let ArithFor {
+            init,
+            cond,
+            step,
+            body,
+        } = arith_for;
+        self.eval_arith(&init)?;
+        let mut status = exit_zero();
+        while cond.trim().is_empty() || self.eval_arith(&cond)? != 0 {
+            status = self.exec(*body.clone(), None, None, None)?;
+            if self.loop_should_exit() {
+                break;
+            }
+            self.eval_arith(&step)?;
+        }
+        Ok(status)
+    }
+
+    /// Expands `$` references, then evaluates the text as a 64-bit integer
+    /// expression, an empty one being 0
+    pub fn eval_arith(&mut self, expr: &str) -> TrshResult<i64> {
+        self.eval_arith_at(expr, 0)
+    }
+
+    fn eval_arith_at(&mut self, expr: &str, depth: usize) -> TrshResult<i64> {
+        if depth > MAX_DEPTH {
+            return Err(TrshError::gen_exec(
+                expr,
+                "expression recursion level exceeded",
+            ));
+        }
+        let expanded = self.expand_quoted(expr)?.join(" ");
+        let tokens = tokenize(&expanded)?;
+        if tokens.is_empty() {
+            return Ok(0);
+        }
+        let parsed = ArithParser {
+            expr: &expanded,
+            tokens,
+            pos: 0,
+        }
+        .parse()?;
+        self.eval_expr(&expanded, &parsed, depth)
+    }
+
+    fn eval_expr(&mut self, text: &str, expr: &ArithExpr, depth: usize) -> TrshResult<i64> {
+        Ok(match expr {
+            ArithExpr::Num(n) => *n,
+            ArithExpr::Var(name, sub) => self.arith_var(name, sub.as_deref(), depth)?,
+            ArithExpr::Unary(op, operand) => {
+                let n = self.eval_expr(text, operand, depth)?;
+                match *op {
+                    "!" => (n == 0) as i64,
+                    "~" => !n,
+                    "-" => n.wrapping_neg(),
+                    _ => n,
+                }
+            }
+            ArithExpr::Binary(left, "&&", right) => {
+                (self.eval_expr(text, left, depth)? != 0
+                    && self.eval_expr(text, right, depth)? != 0) as i64
+            }
+            ArithExpr::Binary(left, "||", right) => {
+                (self.eval_expr(text, left, depth)? != 0
+                    || self.eval_expr(text, right, depth)? != 0) as i64
+            }
+            ArithExpr::Binary(left, op, right) => {
+                let l = self.eval_expr(text, left, depth)?;
+                let r = self.eval_expr(text, right, depth)?;
+                apply(text, op, l, r)?
+            }
+            ArithExpr::Ternary(cond, then, otherwise) => {
+                match self.eval_expr(text, cond, depth)? {
+                    0 => self.eval_expr(text, otherwise, depth)?,
+                    _ => self.eval_expr(text, then, depth)?,
+                }
+            }
+            ArithExpr::Assign(name, sub, op, value) => {
+                let value = self.eval_expr(text, value, depth)?;
+                let value = match op.strip_suffix('=').filter(|op| !op.is_empty()) {
+                    Some(op) => {
+                        let current = self.arith_var(name, sub.as_deref(), depth)?;
+                        apply(text, op, current, value)?
+                    }
+                    None => value,
+                };
+                self.set_arith_var(name, sub.as_deref(), value, depth)?;
+                value
+            }
+            ArithExpr::Step {
+                name,
+                sub,
+                delta,
+                post,
+            } => {
+                let current = self.arith_var(name, sub.as_deref(), depth)?;
+                let value = current.wrapping_add(*delta);
+                self.set_arith_var(name, sub.as_deref(), value, depth)?;
+                if *post { current } else { value }
+            }
+        })
+    }
+
+    /// The value of a variable in an expression. Unset or empty is 0, and
+    /// anything that isn't a number is evaluated as an expression itself
+    fn arith_var(&mut self, name: &str, sub: Option<&str>, depth: usize) -> TrshResult<i64> {
+        let value = match sub {
+            Some(sub) => {
+                let key = self.arith_subscript(name, sub, depth)?;
+                self.expand_param(&format!("{name}[{key}]"), true)?
+                    .join(" ")
+            }
+            None => self.var(name).unwrap_or_default().to_owned(),
+        };
+        let value = value.trim();
+        match value.parse() {
+            Ok(n) => Ok(n),
+            Err(_) if value.is_empty() => Ok(0),
+            Err(_) => self.eval_arith_at(value, depth + 1),
+        }
+    }
+
+    fn set_arith_var(
+        &mut self,
+        name: &str,
+        sub: Option<&str>,
+        value: i64,
+        depth: usize,
+    ) -> TrshResult<()> {
+        let index = sub
+            .map(|sub| self.arith_subscript(name, sub, depth))
+            .transpose()?;
+        self.assign(Assignment {
+            name: name.to_owned(),
+            index,
+            append: false,
+            value: AssignValue::Word(vec![Token::Word(value.to_string())]),
+        })
+    }
+
+    /// Associative arrays take the key as written, indexed ones evaluate it
+    fn arith_subscript(&mut self, name: &str, sub: &str, depth: usize) -> TrshResult<String> {
+        match self.var_value(name) {
+            Some(VarValue::Assoc(_)) => Ok(sub.trim().to_owned()),
+            _ => Ok(self.eval_arith_at(sub, depth + 1)?.to_string()),
+        }
+    }
+}
+
+fn apply(text: &str, op: &str, l: i64, r: i64) -> TrshResult<i64> {
+    Ok(match op {
+        "," => r,
+        "|" => l | r,
+        "^" => l ^ r,
+        "&" => l & r,
+        "==" => (l == r) as i64,
+        "!=" => (l != r) as i64,
+        "<" => (l < r) as i64,
+        ">" => (l > r) as i64,
+        "<=" => (l <= r) as i64,
+        ">=" => (l >= r) as i64,
+        "<<" => l.wrapping_shl(r as u32),
+        ">>" => l.wrapping_shr(r as u32),
+        "+" => l.wrapping_add(r),
+        "-" => l.wrapping_sub(r),
+        "*" => l.wrapping_mul(r),
+        "/" | "%" if r == 0 => {
+            return Err(TrshError::gen_exec(text, "division by 0"));
+        }
+        "/" => l.wrapping_div(r),
+        "%" => l.wrapping_rem(r),
+        "**" => match u32::try_from(r) {
+            Ok(r) => l.wrapping_pow(r),
+            Err(_) if r < 0 => return Err(TrshError::gen_exec(text, "exponent less than 0")),
+            Err(_) => 0,
+        },
+        op => unreachable!("{op}"),
+    })
+}
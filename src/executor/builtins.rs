@@ -1,116 +1,172 @@
 use std::{
-    io::Write,
-    os::unix::process::ExitStatusExt,
-    process::{ExitStatus, Stdio},
+    fs::File,
+    io::{self, Write},
+    process::ExitStatus,
 };
 
 use crate::{TrshError, TrshResult, ast::CmdArg, builtins::Builtin, executor::exit_zero};
 
 use super::{
-    Executor,
+    Executor, LoopCtl,
+    redirect::Streams,
     utils::{BINARY_TESTS, UNARY_TESTS, exit_num},
 };
 
+/// The streams a builtin reads and writes in place of the shell's own, so it
+/// can be redirected or sit in a pipeline like any other command
+pub struct BuiltinIo {
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+}
+
+impl From<Streams> for BuiltinIo {
+    fn from(streams: Streams) -> Self {
+        let stdout: Box<dyn Write> = match streams.stdout {
+            Some(fd) => Box::new(File::from(fd)),
+            None => Box::new(io::stdout()),
+        };
+        let stderr: Box<dyn Write> = match streams.stderr {
+            Some(fd) => Box::new(File::from(fd)),
+            None => Box::new(io::stderr()),
+        };
+        Self { stdout, stderr }
+    }
+}
+
 impl Executor {
     pub fn exec_builtin(
         &mut self,
         builtin: Builtin,
         args: Vec<CmdArg>,
-        stdin: Option<Stdio>,
-        stdout: Option<Stdio>,
+        io: &mut BuiltinIo,
     ) -> TrshResult<ExitStatus> {
         match builtin {
             Builtin::Colon => todo!(),
-            Builtin::Dot => todo!(),
-            Builtin::Alias => self.handle_alias(args),
-            Builtin::Bg => todo!(),
-            Builtin::Break => todo!(),
+            // run by exec_named, with the command's streams in place of the shell's
+            Builtin::Dot | Builtin::Source => unreachable!("sourcing goes through exec_named"),
+            Builtin::Alias => self.handle_alias(args, io),
+            Builtin::Bg => self.exec_bg(args, io),
+            Builtin::Break => self.loop_ctl(args, "break", LoopCtl::Break, io),
             Builtin::Cd => self.exec_cd(args),
-            Builtin::Command => todo!(),
-            Builtin::Continue => todo!(),
-            Builtin::Eval => todo!(),
-            Builtin::Exec => todo!(),
-            Builtin::Exit => std::process::exit(0),
-            Builtin::Export => self.handle_export(args),
+            // these run other commands, so exec_named passes them the streams
+            Builtin::Command => unreachable!("command goes through exec_named"),
+            Builtin::Continue => self.loop_ctl(args, "continue", LoopCtl::Continue, io),
+            // run by exec_named, with the command's streams in place of the shell's
+            Builtin::Eval => unreachable!("eval goes through exec_named"),
+            // exec_named hands it the streams themselves, to keep or install
+            Builtin::Exec => unreachable!("exec goes through exec_named"),
+            Builtin::Exit => self.exec_exit(args),
+            Builtin::Export => self.handle_export(args, io),
             Builtin::Fc => todo!(),
-            Builtin::Fg => todo!(),
+            Builtin::Fg => self.exec_fg(args, io),
             Builtin::Getopts => todo!(),
-            Builtin::Hash => todo!(),
-            Builtin::Jobs => todo!(),
-            Builtin::Kill => todo!(),
+            Builtin::Hash => self.exec_hash(args, io),
+            Builtin::Jobs => self.exec_jobs(args, io),
+            Builtin::Disown => self.exec_disown(args),
+            Builtin::Kill => self.exec_kill(args, io),
             Builtin::Pwd => {
-                println!("{}", self.cwd.display());
+                writeln!(io.stdout, "{}", self.cwd.display())?;
                 Ok(exit_zero())
             }
             Builtin::Read => todo!(),
             Builtin::Readonly => todo!(),
-            Builtin::Return => todo!(),
-            Builtin::Set => todo!(),
+            Builtin::Return => self.exec_return(args),
+            Builtin::Set => self.exec_set(args, io),
             Builtin::Shift => todo!(),
             Builtin::Test => self.handle_test(args),
             Builtin::Times => todo!(),
-            Builtin::Trap => todo!(),
-            Builtin::Type => todo!(),
+            Builtin::Trap => self.exec_trap(args, io),
+            Builtin::Type => self.exec_type(args, io),
             Builtin::Ulimit => todo!(),
             Builtin::Umask => todo!(),
-            Builtin::Unalias => self.unalias(args),
+            Builtin::Unalias => self.unalias(args, io),
             Builtin::Unset => self.unset(args),
-            Builtin::Wait => todo!(),
+            Builtin::Wait => self.exec_wait(args),
             Builtin::Bind => todo!(),
-            Builtin::Builtin => todo!(),
+            Builtin::Builtin => unreachable!("builtin goes through exec_named"),
             Builtin::Caller => todo!(),
-            Builtin::Declare => todo!(),
-            Builtin::Echo => self.echo(args),
+            Builtin::Declare => self.declare(args, io),
+            Builtin::Echo => self.echo(args, io),
             Builtin::Enable => todo!(),
             Builtin::Help => todo!(),
-            Builtin::Let => todo!(),
-            Builtin::Local => todo!(),
+            Builtin::Let => self.exec_let(args),
+            // there are no functions for it to be used in
+            Builtin::Local => Err(TrshError::gen_exec(
+                "local",
+                "can only be used in a function",
+            )),
             Builtin::Logout => todo!(),
             Builtin::Mapfile => todo!(),
             Builtin::Printf => todo!(),
             Builtin::Readarray => todo!(),
-            Builtin::Source => todo!(),
             Builtin::Shopt => todo!(),
             Builtin::True => Ok(exit_zero()),
             Builtin::False => Ok(exit_num(1)),
         }
     }
 
-    fn echo(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
-        println!(
+    /// `break [n]` and `continue [n]`
+    fn loop_ctl(
+        &mut self,
+        args: Vec<CmdArg>,
+        name: &str,
+        ctl: fn(usize) -> LoopCtl,
+        io: &mut BuiltinIo,
+    ) -> TrshResult<ExitStatus> {
+        let n =
+            match args.first() {
+                Some(a) => a.as_str().parse::<usize>().ok().filter(|n| *n > 0).ok_or(
+                    TrshError::gen_exec(name, &format!("{a}: loop count out of range")),
+                )?,
+                None => 1,
+            };
+        if self.loop_depth == 0 {
+            writeln!(
+                io.stderr,
+                "trsh: {name}: only meaningful in a `for', `while', or `until' loop"
+            )?;
+        } else {
+            self.loop_ctl = Some(ctl(n.min(self.loop_depth)));
+        }
+        Ok(exit_zero())
+    }
+
+    fn echo(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        writeln!(
+            io.stdout,
             "{}",
             args.into_iter()
                 .map(|a| match a {
                     CmdArg::Arg(a) => a,
                     CmdArg::Assignment(l, r) => format!("{}={}", l, r),
+                    CmdArg::Declaration(_, text) => text,
                     CmdArg::Quoted(q) => q,
                     CmdArg::OpEq => "=".to_owned(),
                     CmdArg::OpNeq => "!=".to_owned(),
-                    CmdArg::Variable(v) => match self.env_vars.get(&v) {
-                        Some(o) => o.clone(),
+                    CmdArg::Variable(v) => match self.var(&v) {
+                        Some(o) => o.to_owned(),
                         None => "".to_owned(),
                     },
                     CmdArg::CommandSub(_) => todo!(),
                 })
                 .collect::<Vec<String>>()
                 .join(" ")
-        );
+        )?;
         Ok(exit_zero())
     }
 
-    fn handle_alias(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
-        println!("{args:?}");
+    fn handle_alias(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
         if args.is_empty() {
-            self.aliases.iter().for_each(|(k, v)| {
-                println!("alias {k}=\"{v}\"");
-            });
-            Ok(ExitStatus::from_raw(0))
+            for (k, v) in &self.aliases {
+                writeln!(io.stdout, "alias {k}=\"{v}\"")?;
+            }
+            Ok(exit_zero())
         } else {
             for a in args {
                 match a {
                     CmdArg::Arg(a) => match self.aliases.get(a.as_str()) {
-                        Some(v) => println!("alias {a}=\"{v}\""),
-                        None => eprintln!("trsh: alias: {a}: not found"),
+                        Some(v) => writeln!(io.stdout, "alias {a}=\"{v}\"")?,
+                        None => writeln!(io.stderr, "trsh: alias: {a}: not found")?,
                     },
                     CmdArg::Assignment(l, r) => {
                         self.aliases.insert(l, r);
@@ -120,6 +176,7 @@ impl Executor {
                     CmdArg::OpNeq => todo!(),
                     CmdArg::Variable(_) => todo!(),
                     CmdArg::CommandSub(_) => todo!(),
+                    CmdArg::Declaration(..) => unreachable!("only declaration builtins get these"),
                 }
                 // match a {
                 //     Token::Word(s) | Token::Quote(s) => match self.aliases.get(s.as_str()) {
@@ -139,52 +196,54 @@ impl Executor {
         }
     }
 
-    fn handle_export(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
-        if args.is_empty() {
-            self.env_vars.iter().for_each(|(k, v)| {
-                println!("declare -x {k}=\"{v}\"");
-            });
-            Ok(exit_zero())
-        } else {
-            for a in args {
-                match a {
-                    CmdArg::Arg(a) => {
-                        self.env_vars.entry(a).or_default();
-                    }
-                    CmdArg::Assignment(l, r) => {
-                        self.env_vars.insert(l, r);
+    /// `export [-n|-p] [name[=value]...]`
+    fn handle_export(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        let mut exported = true;
+        let mut names = Vec::new();
+        for a in args {
+            match a {
+                CmdArg::Arg(flags) if flags.starts_with('-') => {
+                    for c in flags.chars().skip(1) {
+                        match c {
+                            'n' => exported = false,
+                            'p' => (),
+                            c => {
+                                return Err(TrshError::gen_usage(
+                                    "export",
+                                    &format!("-{c}: invalid option"),
+                                ));
+                            }
+                        }
                     }
-                    CmdArg::Quoted(_) => todo!(),
-                    CmdArg::OpEq => todo!(),
-                    CmdArg::OpNeq => todo!(),
-                    CmdArg::Variable(_) => todo!(),
-                    CmdArg::CommandSub(_) => todo!(),
                 }
-                // match a {
-                //     Token::Quote(_) => (),
-                //     Token::Word(s) => {
-                //         if !self.env_vars.contains_key(s.as_str()) {
-                //             self.env_vars.insert(s.to_string(), "".to_string());
-                //         }
-                //     }
-                //     e @ (Token::Eq | Token::Neq) => {
-                //         eprintln!("trsh: export: {e}: not a valid identifier")
-                //     }
-                //     Token::Assignment(left, right) => {
-                //         self.env_vars.insert(left.to_string(), right.to_string());
-                //     }
-                // };
+                CmdArg::Assignment(l, r) => {
+                    self.set_var(&l, r)?;
+                    names.push(l);
+                }
+                CmdArg::Declaration(a, _) => {
+                    names.push(a.name.clone());
+                    self.assign(a)?;
+                }
+                a => names.push(a.into_string()),
             }
-            Ok(exit_zero())
         }
+        if names.is_empty() {
+            for (k, v) in self.exported() {
+                writeln!(io.stdout, "declare -x {k}=\"{v}\"")?;
+            }
+        }
+        for name in names {
+            self.set_exported(&name, exported);
+        }
+        Ok(exit_zero())
     }
-    fn unalias(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
+    fn unalias(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
         if args.is_empty() {
-            println!("nalias: usage: unalias [-a] name [name ...]");
+            writeln!(io.stdout, "nalias: usage: unalias [-a] name [name ...]")?;
             Ok(exit_zero())
         } else {
             for a in args {
-                match self.aliases.remove(a.as_str()) {
+                match self.aliases.remove(a.as_str().as_ref()) {
                     Some(_) => (),
                     None => return Err(TrshError::gen_exec("unalias", &format!("{a}: not found"))),
                 }
@@ -199,10 +258,22 @@ impl Executor {
         // }
     }
 
+    /// `exit [n]`, with no `n` the status of the last command
+    fn exec_exit(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
+        let code = match args.first() {
+            Some(arg) => arg.as_str().parse::<i32>().unwrap_or_else(|_| {
+                eprintln!("trsh: exit: {}: numeric argument required", arg.as_str());
+                2
+            }),
+            None => self.last_status,
+        };
+        self.exit(code)
+    }
+
     fn unset(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
         if !args.is_empty() {
             for a in args {
-                self.env_vars.remove(a.as_str());
+                self.unset_var(&a.as_str())?;
             }
         }
         Ok(exit_zero())
@@ -212,22 +283,16 @@ impl Executor {
         match args.len() {
             1 => todo!("{args:?}"),
             2 => UNARY_TESTS
-                .get(args[0].as_str())
-                .map(|t| t(args[1].as_str()))
-                .map(|tf| {
-                    if tf {
-                        exit_zero()
-                    } else {
-                        ExitStatus::from_raw(1)
-                    }
-                })
+                .get(&args[0].as_str())
+                .map(|t| t(&args[1].as_str()))
+                .map(|tf| if tf { exit_zero() } else { exit_num(1) })
                 .ok_or(TrshError::gen_exec(
                     "test",
                     &format!("invalid test: {}", args[0]),
                 )),
             3 => BINARY_TESTS
-                .get(args[1].as_str())
-                .map(|bt| bt.compare(args[0].as_str(), args[2].as_str()))
+                .get(&args[1].as_str())
+                .map(|bt| bt.compare(&args[0].as_str(), &args[2].as_str()))
                 .unwrap_or(Err(TrshError::gen_exec("test", "invalid cmd"))),
             _ => Err(TrshError::gen_exec(
                 "test",
@@ -0,0 +1,212 @@
+use std::{io::Write, path::PathBuf, process::ExitStatus};
+
+use crate::{
+    TrshError, TrshResult,
+    ast::CmdArg,
+    builtins::{BUILTINS, CmdName, KEYWORDS},
+};
+
+use super::{
+    DEFAULT_PATH, Executor,
+    builtins::BuiltinIo,
+    redirect::Streams,
+    utils::{exit_num, exit_zero},
+};
+
+/// One of the things a command's name can refer to
+enum Found {
+    Alias(String),
+    Keyword,
+    Function,
+    Builtin,
+    /// A file, and whether its location came from the hash table
+    File(PathBuf, bool),
+}
+
+impl Found {
+    /// The word `type -t` prints
+    fn kind(&self) -> &'static str {
+        match self {
+            Found::Alias(_) => "alias",
+            Found::Keyword => "keyword",
+            Found::Function => "function",
+            Found::Builtin => "builtin",
+            Found::File(..) => "file",
+        }
+    }
+
+    /// How `type` and `command -V` describe it
+    fn describe(&self, name: &str) -> String {
+        match self {
+            Found::Alias(alias) => format!("{name} is aliased to `{alias}'"),
+            Found::Keyword => format!("{name} is a shell keyword"),
+            Found::Function => format!("{name} is a function"),
+            Found::Builtin => format!("{name} is a shell builtin"),
+            Found::File(path, true) => format!("{name} is hashed ({})", path.display()),
+            Found::File(path, false) => format!("{name} is {}", path.display()),
+        }
+    }
+}
+
+/// Splits the leading `-xyz` words off a builtin's arguments, stopping at
+/// `--`, and checks each letter is one of `allowed`
+fn split_flags(
+    builtin: &str,
+    args: Vec<CmdArg>,
+    allowed: &str,
+) -> TrshResult<(Vec<char>, Vec<CmdArg>)> {
+    let mut args = args.into_iter().peekable();
+    let mut flags = Vec::new();
+    while let Some(CmdArg::Arg(arg)) =
+        args.next_if(|a| matches!(a, CmdArg::Arg(s) if s.starts_with('-') && s.len() > 1))
+    {
+        if arg == "--" {
+            break;
+        }
+        for c in arg.chars().skip(1) {
+            if !allowed.contains(c) {
+                return Err(TrshError::gen_usage(
+                    builtin,
+                    &format!("-{c}: invalid option"),
+                ));
+            }
+            flags.push(c);
+        }
+    }
+    Ok((flags, args.collect()))
+}
+
+impl Executor {
+    /// What `name` could run, in the order the shell tries them, stopping at
+    /// the first unless `all`. Searching `paths` in place of PATH only looks
+    /// for files
+    fn find_command(&self, name: &str, all: bool, paths: Option<&str>) -> Vec<Found> {
+        let mut found = Vec::new();
+        if paths.is_none() && !name.contains('/') {
+            let (aliases, functions) = self.env();
+            if let Some(alias) = aliases.get(name) {
+                found.push(Found::Alias(alias.clone()));
+            }
+            if KEYWORDS.contains(&name) {
+                found.push(Found::Keyword);
+            }
+            if functions.contains_key(name) {
+                found.push(Found::Function);
+            }
+            if BUILTINS.contains_key(name) {
+                found.push(Found::Builtin);
+            }
+        }
+        if !all && !found.is_empty() {
+            found.truncate(1);
+            return found;
+        }
+        match self.hashed.get(name).filter(|_| !all && paths.is_none()) {
+            Some(path) => found.push(Found::File(path.to_owned(), true)),
+            None => {
+                let paths = paths.unwrap_or(self.var("PATH").unwrap_or(DEFAULT_PATH));
+                let files = self.command_paths(name, paths);
+                let files = files.take(if all { usize::MAX } else { 1 });
+                found.extend(files.map(|path| Found::File(path, false)));
+            }
+        }
+        found
+    }
+
+    /// `type [-a] [-t|-p|-P] name...`
+    pub fn exec_type(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        let (flags, args) = split_flags("type", args, "atpP")?;
+        let all = flags.contains(&'a');
+        let kind = flags.contains(&'t');
+        let force_path = flags.contains(&'P');
+        let path_only = force_path || flags.contains(&'p');
+        let mut status = 0;
+        for name in args.into_iter().map(CmdArg::into_string) {
+            let name = name.as_str();
+            let paths = force_path.then(|| self.var("PATH").unwrap_or(DEFAULT_PATH));
+            let found = self.find_command(name, all, paths);
+            if found.is_empty() {
+                if !kind && !path_only {
+                    writeln!(io.stderr, "trsh: type: {name}: not found")?;
+                }
+                status = 1;
+            }
+            for f in &found {
+                match f {
+                    f if kind => writeln!(io.stdout, "{}", f.kind())?,
+                    Found::File(path, _) if path_only => writeln!(io.stdout, "{}", path.display())?,
+                    _ if path_only => (),
+                    f => writeln!(io.stdout, "{}", f.describe(name))?,
+                }
+            }
+        }
+        Ok(exit_num(status))
+    }
+
+    /// `command [-p] [-v|-V] name [args...]`: runs `name` passing over
+    /// aliases and functions, or with `-v`/`-V` says what it is
+    pub fn exec_command(&mut self, args: Vec<CmdArg>, streams: Streams) -> TrshResult<ExitStatus> {
+        let (flags, mut args) = split_flags("command", args, "pvV")?;
+        let paths = flags.contains(&'p').then_some(DEFAULT_PATH);
+        if args.is_empty() {
+            return Ok(exit_zero());
+        }
+        if flags.contains(&'v') || flags.contains(&'V') {
+            let verbose = flags.contains(&'V');
+            let mut io = BuiltinIo::from(streams);
+            let mut status = 0;
+            for name in args.into_iter().map(CmdArg::into_string) {
+                let name = name.as_str();
+                match self.find_command(name, false, paths).first() {
+                    Some(f) if verbose => writeln!(io.stdout, "{}", f.describe(name))?,
+                    Some(Found::Alias(alias)) => writeln!(io.stdout, "alias {name}='{alias}'")?,
+                    Some(Found::File(path, _)) => writeln!(io.stdout, "{}", path.display())?,
+                    Some(_) => writeln!(io.stdout, "{name}")?,
+                    None => {
+                        if verbose {
+                            writeln!(io.stderr, "trsh: command: {name}: not found")?;
+                        }
+                        status = 1;
+                    }
+                }
+            }
+            io.stdout.flush()?;
+            io.stderr.flush()?;
+            return Ok(exit_num(status));
+        }
+        let name = args.remove(0).into_string();
+        match (CmdName::plain(&name), paths) {
+            (CmdName::Unknown(_), Some(paths)) => {
+                let path = self.command_paths(&name, paths).next();
+                match path {
+                    Some(path) => self.exec_program(name, path, args, streams),
+                    None => {
+                        eprintln!("trsh: {name}: command not found");
+                        Ok(exit_num(127))
+                    }
+                }
+            }
+            (cmd_name, _) => self.exec_cmd_name(cmd_name, args, streams),
+        }
+    }
+
+    /// `builtin name [args...]`: runs the builtin even if something else has
+    /// its name
+    pub fn exec_builtin_cmd(
+        &mut self,
+        mut args: Vec<CmdArg>,
+        streams: Streams,
+    ) -> TrshResult<ExitStatus> {
+        if args.is_empty() {
+            return Ok(exit_zero());
+        }
+        let name = args.remove(0).into_string();
+        match BUILTINS.get(name.as_str()) {
+            Some(builtin) => self.exec_named(CmdName::Builtin(*builtin), args, streams),
+            None => {
+                eprintln!("trsh: builtin: {name}: not a shell builtin");
+                Ok(exit_num(1))
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::{collections::BTreeMap, process::ExitStatus};
+
+use regex::Regex;
+
+use crate::{
+    TrshError, TrshResult,
+    ast::{CondExpr, Token, Word},
+};
+
+use super::{
+    Executor, VarValue,
+    utils::{BINARY_TESTS, UNARY_TESTS, escape_pattern, exit_num, exit_zero, pattern_match},
+};
+
+impl Executor {
+    pub fn exec_cond_test(&mut self, expr: &CondExpr) -> TrshResult<ExitStatus> {
+        if self.eval_cond(expr)? {
+            Ok(exit_zero())
+        } else {
+            Ok(exit_num(1))
+        }
+    }
+
+    fn eval_cond(&mut self, expr: &CondExpr) -> TrshResult<bool> {
+        Ok(match expr {
+            CondExpr::And(left, right) => self.eval_cond(left)? && self.eval_cond(right)?,
+            CondExpr::Or(left, right) => self.eval_cond(left)? || self.eval_cond(right)?,
+            CondExpr::Not(expr) => !self.eval_cond(expr)?,
+            CondExpr::Word(word) => !self.cond_string(word)?.is_empty(),
+            CondExpr::Unary(op, word) => {
+                let arg = self.cond_string(word)?;
+                UNARY_TESTS
+                    .get(op)
+                    .map(|t| t(&arg))
+                    .ok_or(TrshError::gen_exec(
+                        "[[",
+                        &format!("{op}: unary operator expected"),
+                    ))?
+            }
+            CondExpr::Binary(left, op, right) => {
+                let left = self.cond_string(left)?;
+                match op.as_str() {
+                    "==" | "=" => pattern_match(&self.cond_pattern(right)?, &left),
+                    "!=" => !pattern_match(&self.cond_pattern(right)?, &left),
+                    "<" => left < self.cond_string(right)?,
+                    ">" => left > self.cond_string(right)?,
+                    op => {
+                        let right = self.cond_string(right)?;
+                        BINARY_TESTS
+                            .get(op)
+                            .and_then(|bt| bt.test(&left, &right))
+                            .ok_or(TrshError::gen_exec(
+                                "[[",
+                                &format!("{left} {op} {right}: integer expression expected"),
+                            ))?
+                    }
+                }
+            }
+            CondExpr::Regex(left, right) => {
+                let left = self.cond_string(left)?;
+                let re = Regex::new(&self.cond_regex(right)?)
+                    .map_err(|e| TrshError::gen_exec("[[", &format!("{e}")))?;
+                match re.captures(&left) {
+                    Some(caps) => {
+                        let groups = caps
+                            .iter()
+                            .map(|m| m.map_or("", |m| m.as_str()).to_owned())
+                            .enumerate()
+                            .collect();
+                        self.put_value("BASH_REMATCH", VarValue::Indexed(groups));
+                        true
+                    }
+                    None => {
+                        self.put_value("BASH_REMATCH", VarValue::Indexed(BTreeMap::new()));
+                        false
+                    }
+                }
+            }
+        })
+    }
+
+    /// Expands a word to a plain string, there's no splitting or globbing in `[[ ]]`
+    fn cond_string(&self, word: &Word) -> TrshResult<String> {
+        self.expand_word(word)
+    }
+
+    /// Like `cond_string`, but quoted parts match literally
+    fn cond_pattern(&self, word: &Word) -> TrshResult<String> {
+        word.iter()
+            .map(|t| match t {
+                Token::VarExp(v) => Ok(self.expand_param(v, true)?.join(" ")),
+                Token::Quote(q) => Ok(escape_pattern(&self.expand_quoted(q)?.join(" "))),
+                Token::SingleQuote(q) => Ok(escape_pattern(q)),
+                t => Ok(t.to_string()),
+            })
+            .collect()
+    }
+
+    /// Like `cond_string`, but quoted parts match literally
+    fn cond_regex(&self, word: &Word) -> TrshResult<String> {
+        word.iter()
+            .map(|t| match t {
+                Token::VarExp(v) => Ok(self.expand_param(v, true)?.join(" ")),
+                Token::Quote(q) => Ok(regex::escape(&self.expand_quoted(q)?.join(" "))),
+                Token::SingleQuote(q) => Ok(regex::escape(q)),
+                t => Ok(t.to_string()),
+            })
+            .collect()
+    }
+}
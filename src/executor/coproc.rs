@@ -0,0 +1,100 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    process::ExitStatus,
+};
+
+use nix::{
+    fcntl::{FcntlArg, FdFlag, fcntl},
+    sys::wait::{WaitPidFlag, WaitStatus, waitpid},
+    unistd::{Pid, dup},
+};
+
+use crate::{TrshError, TrshResult, ast::Coproc};
+
+use super::{Executor, VarValue, utils::exit_zero};
+
+/// The shell's ends of a running coproc's pipes, closed when dropped. They're
+/// close-on-exec, and forked children close them too, so only the shell
+/// keeps the coproc's input open
+pub struct CoprocFds {
+    pid: Pid,
+    _read: OwnedFd,
+    _write: OwnedFd,
+}
+
+impl Executor {
+    /// Forks a child running the body with its stdin and stdout on pipes, and
+    /// sets `NAME` to `(read_fd write_fd)` and `NAME_PID` to the child's pid
+    pub fn exec_coproc(&mut self, coproc: Coproc) -> TrshResult<ExitStatus> {
+        let Coproc { name, body } = coproc;
+        let (from_child, child_out) = os_pipe::pipe()?;
+        let (child_in, to_child) = os_pipe::pipe()?;
+        let child = self.fork_subshell(
+            *body,
+            None,
+            &[(child_in.as_raw_fd(), 0), (child_out.as_raw_fd(), 1)],
+            &[from_child.as_raw_fd(), to_child.as_raw_fd()],
+        )?;
+        drop(child_in);
+        drop(child_out);
+        let read = OwnedFd::from(from_child);
+        let write = OwnedFd::from(to_child);
+        for fd in [&read, &write] {
+            fcntl(fd.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))
+                .map_err(io::Error::from)?;
+        }
+        let fds = BTreeMap::from([
+            (0, read.as_raw_fd().to_string()),
+            (1, write.as_raw_fd().to_string()),
+        ]);
+        self.put_value(&name, VarValue::Indexed(fds));
+        self.set_var(&format!("{name}_PID"), child.to_string())?;
+        if let Some(old) = self.coprocs.insert(
+            name,
+            CoprocFds {
+                pid: child,
+                _read: read,
+                _write: write,
+            },
+        ) {
+            let _ = waitpid(old.pid, Some(WaitPidFlag::WNOHANG));
+        }
+        Ok(exit_zero())
+    }
+
+    /// Drops the coprocs that have exited, closing the shell's ends of their
+    /// pipes and unsetting `NAME` and `NAME_PID`
+    pub fn reap_coprocs(&mut self) {
+        let done: Vec<_> = self
+            .coprocs
+            .iter()
+            .filter(|(_, fds)| {
+                // `wait -n` may have reaped it already
+                !matches!(
+                    waitpid(fds.pid, Some(WaitPidFlag::WNOHANG)),
+                    Ok(WaitStatus::StillAlive)
+                )
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in done {
+            self.coprocs.remove(&name);
+            let _ = self.unset_var(&name);
+            let _ = self.unset_var(&format!("{name}_PID"));
+        }
+    }
+
+    /// Duplicates the fd a `>&fd`/`<&fd` redirection names, so the child can
+    /// take it over without closing the shell's copy
+    pub fn redirect_fd(&self, target: &str) -> TrshResult<OwnedFd> {
+        let target = self.expand_quoted(target)?.join(" ");
+        let fd: RawFd = target
+            .parse()
+            .map_err(|_| TrshError::gen_exec(&target, "ambiguous redirect"))?;
+        let new_fd = dup(fd).map_err(|e| TrshError::gen_exec(&target, e.desc()))?;
+        // SAFETY: dup just handed us this fd and nothing else owns it
+        Ok(unsafe { OwnedFd::from_raw_fd(new_fd) })
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+};
+
+use crate::{TrshError, TrshResult, ast::CmdArg};
+
+use super::{
+    Executor,
+    builtins::BuiltinIo,
+    utils::{exit_num, exit_zero, is_executable},
+};
+
+/// Where a command was found on PATH, and how often it's been run from there
+pub struct Hashed {
+    path: PathBuf,
+    hits: usize,
+}
+
+/// The remembered locations of commands, so PATH is only searched the first
+/// time one is run
+#[derive(Default)]
+pub struct HashTable(BTreeMap<String, Hashed>);
+
+impl HashTable {
+    pub fn get(&self, name: &str) -> Option<&Path> {
+        self.0.get(name).map(|h| h.path.as_path())
+    }
+
+    fn insert(&mut self, name: &str, path: PathBuf) {
+        self.0.insert(name.to_owned(), Hashed { path, hits: 0 });
+    }
+}
+
+impl Executor {
+    /// Finds a command through the hash table, searching PATH and remembering
+    /// the result when it isn't there or what it remembered has gone
+    pub fn hashed_command(&mut self, name: &str) -> Option<PathBuf> {
+        if name.contains('/') {
+            return self.lookup_command(name);
+        }
+        let found = self
+            .hashed
+            .0
+            .get(name)
+            .is_some_and(|h| h.path.is_file() && is_executable(&h.path));
+        if !found {
+            let path = self.lookup_command(name)?;
+            self.hashed.insert(name, path);
+        }
+        let hashed = self.hashed.0.get_mut(name)?;
+        hashed.hits += 1;
+        Some(hashed.path.clone())
+    }
+
+    /// Forgets every remembered location, as assigning PATH does
+    pub fn path_changed(&mut self) {
+        self.hashed.0.clear();
+    }
+
+    /// `hash [-r] [-d] [-t] [-p path] [name...]`
+    pub fn exec_hash(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        let mut args = args.into_iter().map(CmdArg::into_string).peekable();
+        let mut path = None;
+        let mut delete = false;
+        let mut show = false;
+        let mut reset = false;
+        while let Some(flags) = args.next_if(|a| a.starts_with('-') && a.len() > 1) {
+            if flags == "--" {
+                break;
+            }
+            for c in flags.chars().skip(1) {
+                match c {
+                    'r' => {
+                        self.path_changed();
+                        reset = true;
+                    }
+                    'd' => delete = true,
+                    't' => show = true,
+                    'p' => {
+                        let p = args.next().ok_or(TrshError::gen_usage(
+                            "hash",
+                            "-p: option requires an argument",
+                        ))?;
+                        path = Some(self.cwd.join(p));
+                    }
+                    c => {
+                        return Err(TrshError::gen_usage(
+                            "hash",
+                            &format!("-{c}: invalid option"),
+                        ));
+                    }
+                }
+            }
+        }
+        let names: Vec<String> = args.collect();
+        if names.is_empty() {
+            if show || delete || path.is_some() {
+                return Err(TrshError::gen_usage("hash", "name required"));
+            }
+            if self.hashed.0.is_empty() {
+                if !reset {
+                    writeln!(io.stdout, "hash: hash table empty")?;
+                }
+            } else {
+                writeln!(io.stdout, "hits\tcommand")?;
+                for hashed in self.hashed.0.values() {
+                    writeln!(io.stdout, "{:4}\t{}", hashed.hits, hashed.path.display())?;
+                }
+            }
+            return Ok(exit_zero());
+        }
+        let mut status = 0;
+        for name in &names {
+            if let Some(path) = &path {
+                self.hashed.insert(name, path.clone());
+            } else if delete {
+                if self.hashed.0.remove(name).is_none() {
+                    writeln!(io.stderr, "trsh: hash: {name}: not found")?;
+                    status = 1;
+                }
+            } else if show {
+                match self.hashed.0.get(name) {
+                    Some(hashed) if names.len() == 1 => {
+                        writeln!(io.stdout, "{}", hashed.path.display())?
+                    }
+                    Some(hashed) => writeln!(io.stdout, "{name}\t{}", hashed.path.display())?,
+                    None => {
+                        writeln!(io.stderr, "trsh: hash: {name}: not found")?;
+                        status = 1;
+                    }
+                }
+            } else if !name.contains('/') {
+                match self.lookup_command(name) {
+                    Some(path) => self.hashed.insert(name, path),
+                    None => {
+                        writeln!(io.stderr, "trsh: hash: {name}: not found")?;
+                        status = 1;
+                    }
+                }
+            }
+        }
+        Ok(exit_num(status))
+    }
+}
@@ -0,0 +1,872 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Write},
+    os::{
+        fd::{AsRawFd, BorrowedFd},
+        unix::process::ExitStatusExt,
+    },
+    process::ExitStatus,
+};
+
+use nix::{
+    errno::Errno,
+    sys::{
+        signal::{SigHandler, Signal, kill, killpg, signal},
+        termios::{SetArg, Termios, tcgetattr, tcsetattr},
+        wait::{WaitPidFlag, WaitStatus, waitpid},
+    },
+    unistd::{Pid, getpgrp, isatty, setpgid, tcgetpgrp, tcsetpgrp},
+};
+
+use crate::{
+    ExecError, TrshError, TrshResult,
+    ast::{CmdArg, Command},
+};
+
+use super::{
+    Executor,
+    builtins::BuiltinIo,
+    utils::{exit_num, exit_zero, parse_signal, signal_description, status_code},
+};
+
+/// Ignored by an interactive shell, so the keys that interrupt, quit and stop
+/// only reach its foreground job
+pub const TERMINAL_SIGNALS: [Signal; 5] = [
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+    Signal::SIGTSTP,
+    Signal::SIGTTIN,
+    Signal::SIGTTOU,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Stopped,
+    Done(ExitStatus),
+}
+
+pub struct Job {
+    pgid: Pid,
+    /// Each process in the job, with its status once it has finished
+    procs: Vec<(Pid, Option<ExitStatus>)>,
+    text: String,
+    state: JobState,
+    /// Set when the state changes, until the change is reported
+    changed: bool,
+    /// The terminal modes the job had when it was stopped
+    tmodes: Option<Termios>,
+}
+
+impl Job {
+    fn new(start: JobStart, text: String, state: JobState) -> Self {
+        Self {
+            pgid: start.pgid.unwrap_or(start.procs[0].0),
+            procs: start.procs,
+            text,
+            state,
+            changed: false,
+            tmodes: None,
+        }
+    }
+
+    fn set_state(&mut self, state: JobState) {
+        if self.state != state {
+            self.state = state;
+            self.changed = true;
+        }
+    }
+
+    fn unfinished(&self) -> Vec<Pid> {
+        self.procs
+            .iter()
+            .filter(|(_, status)| status.is_none())
+            .map(|(pid, _)| *pid)
+            .collect()
+    }
+
+    /// The job is done once every process is, with the last one's status
+    fn finish(&mut self) {
+        if let [.., (_, Some(status))] = self.procs[..]
+            && self.procs.iter().all(|(_, s)| s.is_some())
+        {
+            self.set_state(JobState::Done(status));
+        }
+    }
+
+    fn state_name(&self) -> String {
+        match self.state {
+            JobState::Running => "Running".to_owned(),
+            JobState::Stopped => "Stopped".to_owned(),
+            JobState::Done(status) if status.success() => "Done".to_owned(),
+            JobState::Done(status) => match status.code() {
+                Some(code) => format!("Exit {code}"),
+                None => signal_description(status).unwrap_or("Interrupt".to_owned()),
+            },
+        }
+    }
+}
+
+/// The processes started so far for the job being run, which all join one
+/// process group
+pub struct JobStart {
+    pgid: Option<Pid>,
+    foreground: bool,
+    procs: Vec<(Pid, Option<ExitStatus>)>,
+    stopped: bool,
+}
+
+impl JobStart {
+    fn new(foreground: bool) -> Self {
+        Self {
+            pgid: None,
+            foreground,
+            procs: Vec::new(),
+            stopped: false,
+        }
+    }
+}
+
+/// What a child does before running anything, while the shell has job control
+#[derive(Clone, Copy)]
+pub struct ChildSetup {
+    /// 0 for a group of its own
+    pgid: Pid,
+    foreground: bool,
+}
+
+impl ChildSetup {
+    /// Joins the job's process group, takes the terminal if it's in the
+    /// foreground, and undoes the shell ignoring the terminal's signals
+    pub fn apply(self) {
+        let _ = setpgid(Pid::from_raw(0), self.pgid);
+        if self.foreground {
+            // SAFETY: fd 0 outlives the call. `io::stdin()` would take a
+            // lock, which isn't safe between fork and exec
+            let stdin = unsafe { BorrowedFd::borrow_raw(0) };
+            let _ = tcsetpgrp(stdin, getpgrp());
+        }
+        default_terminal_signals();
+    }
+}
+
+/// Undoes the shell ignoring the terminal's signals, for what it runs
+pub fn default_terminal_signals() {
+    for sig in TERMINAL_SIGNALS {
+        // SAFETY: setting the default handler
+        let _ = unsafe { signal(sig, SigHandler::SigDfl) };
+    }
+}
+
+#[derive(Default)]
+pub struct JobTable {
+    jobs: BTreeMap<usize, Job>,
+    /// Job ids by when they were last stopped or put in the background, the
+    /// last is `%+` and the one before it `%-`
+    recent: Vec<usize>,
+    /// The statuses of processes whose jobs have left the table, so `wait
+    /// pid` still has them
+    reaped: BTreeMap<Pid, ExitStatus>,
+}
+
+impl JobTable {
+    fn insert(&mut self, job: Job) -> usize {
+        let id = self.jobs.keys().next_back().map_or(1, |id| id + 1);
+        // a reused pid isn't the process that had it before
+        for (pid, _) in &job.procs {
+            self.reaped.remove(pid);
+        }
+        self.jobs.insert(id, job);
+        self.recent.push(id);
+        id
+    }
+
+    fn remove(&mut self, id: usize) -> Option<Job> {
+        self.recent.retain(|r| *r != id);
+        let job = self.jobs.remove(&id)?;
+        for (pid, status) in &job.procs {
+            if let Some(status) = status {
+                self.reaped.insert(*pid, *status);
+            }
+        }
+        Some(job)
+    }
+
+    /// The status of a process already waited for, in a job or out of one
+    fn finished(&self, pid: Pid) -> Option<ExitStatus> {
+        let mut procs = self.jobs.values().flat_map(|job| &job.procs);
+        match procs.find(|(p, _)| *p == pid) {
+            Some((_, status)) => *status,
+            None => self.reaped.get(&pid).copied(),
+        }
+    }
+
+    fn touch(&mut self, id: usize) {
+        self.recent.retain(|r| *r != id);
+        self.recent.push(id);
+    }
+
+    fn mark(&self, id: usize) -> char {
+        match self.recent.iter().rev().position(|r| *r == id) {
+            Some(0) => '+',
+            Some(1) => '-',
+            _ => ' ',
+        }
+    }
+
+    /// `%n`, `%+` (or `%%`), `%-`, `%string` for the job whose command starts
+    /// with string, or `%?string` for the one containing it
+    fn resolve(&self, spec: &str) -> Option<usize> {
+        let spec = spec.strip_prefix('%').unwrap_or(spec);
+        match spec {
+            "" | "%" | "+" => return self.recent.last().copied(),
+            "-" => return self.recent.iter().rev().nth(1).copied(),
+            _ => (),
+        }
+        if let Ok(id) = spec.parse() {
+            return self.jobs.contains_key(&id).then_some(id);
+        }
+        let matches: Vec<_> = match spec.strip_prefix('?') {
+            Some(s) => self.find(|text| text.contains(s)),
+            None => self.find(|text| text.starts_with(spec)),
+        };
+        match matches[..] {
+            [id] => Some(id),
+            _ => None,
+        }
+    }
+
+    fn find(&self, f: impl Fn(&str) -> bool) -> Vec<usize> {
+        self.jobs
+            .iter()
+            .filter(|(_, job)| f(&job.text))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// A line as `jobs` shows it, `long` adding the process group
+    fn line(&self, id: usize, long: bool) -> String {
+        let job = &self.jobs[&id];
+        let mark = self.mark(id);
+        let state = job.state_name();
+        let amp = if job.state == JobState::Running {
+            " &"
+        } else {
+            ""
+        };
+        match long {
+            true => format!("[{id}]{mark} {} {state:<24}{}{amp}", job.pgid, job.text),
+            false => format!("[{id}]{mark}  {state:<24}{}{amp}", job.text),
+        }
+    }
+}
+
+/// Turns a `waitpid` result into a status, `None` if the process is still
+/// running
+pub fn wait_status(status: WaitStatus) -> Option<ExitStatus> {
+    match status {
+        WaitStatus::Exited(_, code) => Some(exit_num(code)),
+        WaitStatus::Signaled(_, sig, core) => Some(ExitStatus::from_raw(
+            sig as i32 | if core { 0x80 } else { 0 },
+        )),
+        WaitStatus::Stopped(_, sig) => Some(ExitStatus::from_raw(0x7f | (sig as i32) << 8)),
+        _ => None,
+    }
+}
+
+impl Executor {
+    /// Puts the shell in its own process group in charge of the terminal, if
+    /// there is one, so each job can get a group of its own
+    pub fn enable_job_control(&mut self) {
+        let stdin = io::stdin();
+        if !isatty(stdin.as_raw_fd()).unwrap_or(false) {
+            return;
+        }
+        // started in the background by another shell, wait to be brought
+        // into the foreground
+        while tcgetpgrp(&stdin).is_ok_and(|pgrp| pgrp != getpgrp()) {
+            let _ = killpg(getpgrp(), Signal::SIGTTIN);
+        }
+        for sig in TERMINAL_SIGNALS {
+            // SAFETY: ignoring a signal sets no handler
+            let _ = unsafe { signal(sig, SigHandler::SigIgn) };
+        }
+        let _ = setpgid(Pid::from_raw(0), Pid::from_raw(0));
+        let _ = tcsetpgrp(&stdin, getpgrp());
+        self.shell_tmodes = tcgetattr(&stdin).ok();
+        self.job_control = true;
+    }
+
+    /// How a child started now sets itself up, `None` without job control
+    pub fn child_setup(&self) -> Option<ChildSetup> {
+        self.job_control.then(|| ChildSetup {
+            pgid: self
+                .starting
+                .as_ref()
+                .and_then(|s| s.pgid)
+                .unwrap_or(Pid::from_raw(0)),
+            foreground: self.starting.as_ref().is_some_and(|s| s.foreground),
+        })
+    }
+
+    /// The shell's side of a child joining the job being started. Both sides
+    /// set the process group so neither one can run ahead of it
+    pub fn joined_job(&mut self, pid: Pid) {
+        let job_control = self.job_control;
+        if let Some(start) = &mut self.starting {
+            if job_control {
+                let pgid = *start.pgid.get_or_insert(pid);
+                let _ = setpgid(pid, pgid);
+                if start.foreground {
+                    let _ = tcsetpgrp(io::stdin(), pgid);
+                }
+            }
+            start.procs.push((pid, None));
+        }
+    }
+
+    /// Records a status `wait_pid` got for a process of the job being run
+    pub fn job_waited(&mut self, pid: Pid, status: ExitStatus) {
+        let Some(start) = &mut self.starting else {
+            return;
+        };
+        if let Some((_, s)) = start.procs.iter_mut().find(|(p, _)| *p == pid) {
+            match status.stopped_signal() {
+                Some(_) => start.stopped = true,
+                None => *s = Some(status),
+            }
+        }
+    }
+
+    /// Runs a pipeline as a foreground job of its own when the shell has job
+    /// control, otherwise just runs it
+    pub fn exec_job(
+        &mut self,
+        cmd: Command,
+        text: String,
+        stdin: Option<std::os::fd::OwnedFd>,
+        stdout: Option<std::os::fd::OwnedFd>,
+        stderr: Option<std::os::fd::OwnedFd>,
+    ) -> TrshResult<ExitStatus> {
+        let pipeline = match &cmd {
+            Command::Not(cmd) => cmd,
+            cmd => cmd,
+        };
+        if self.job_control
+            && self.starting.is_none()
+            && matches!(pipeline, Command::Simple(_) | Command::Pipeline(..))
+        {
+            self.foreground_job(text, |this| this.exec(cmd, stdin, stdout, stderr))
+        } else {
+            self.exec(cmd, stdin, stdout, stderr)
+        }
+    }
+
+    /// Runs `f` with the processes it starts joining one job in the
+    /// foreground, which goes in the job table if it gets stopped
+    pub fn foreground_job(
+        &mut self,
+        text: String,
+        f: impl FnOnce(&mut Self) -> TrshResult<ExitStatus>,
+    ) -> TrshResult<ExitStatus> {
+        self.starting = Some(JobStart::new(true));
+        let status = f(self);
+        let start = self.starting.take().unwrap();
+        if start.stopped {
+            let mut job = Job::new(start, text, JobState::Stopped);
+            job.tmodes = tcgetattr(io::stdin()).ok();
+            let id = self.jobs.insert(job);
+            eprintln!();
+            eprintln!("{}", self.jobs.line(id, false));
+        }
+        self.take_terminal();
+        self.interrupted(status?)
+    }
+
+    /// A foreground job killed by Ctrl-C interrupts the shell too, giving up
+    /// on the rest of the command line as if the shell had got the SIGINT
+    fn interrupted(&mut self, status: ExitStatus) -> TrshResult<ExitStatus> {
+        match status.signal() {
+            Some(sig) if sig == Signal::SIGINT as i32 => {
+                self.last_status = status_code(status);
+                Err(ExecError::Interrupted.into())
+            }
+            _ => Ok(status),
+        }
+    }
+
+    /// Gives the terminal back to the shell after a foreground job
+    fn take_terminal(&self) {
+        if !self.job_control {
+            return;
+        }
+        let _ = tcsetpgrp(io::stdin(), getpgrp());
+        if let Some(tmodes) = &self.shell_tmodes {
+            let _ = tcsetattr(io::stdin(), SetArg::TCSADRAIN, tmodes);
+        }
+    }
+
+    /// `cmd &`, forks it off as a job and carries on. Without job control its
+    /// stdin is `/dev/null`, so it can't take input meant for the shell
+    pub fn exec_background(&mut self, cmd: Command, text: String) -> TrshResult<ExitStatus> {
+        let stdin = match self.job_control {
+            true => None,
+            false => Some(File::open("/dev/null")?.into()),
+        };
+        let outer = self.starting.replace(JobStart::new(false));
+        let pid = self.fork_subshell(cmd, stdin, &[], &[]);
+        let start = std::mem::replace(&mut self.starting, outer).unwrap();
+        let pid = pid?;
+        let id = self.jobs.insert(Job::new(start, text, JobState::Running));
+        self.last_background = Some(pid);
+        if self.job_control {
+            eprintln!("[{id}] {pid}");
+        }
+        Ok(exit_zero())
+    }
+
+    /// Checks on every job without blocking
+    fn poll_jobs(&mut self) {
+        let flags = WaitPidFlag::WNOHANG | WaitPidFlag::WUNTRACED | WaitPidFlag::WCONTINUED;
+        for job in self.jobs.jobs.values_mut() {
+            let mut state = None;
+            for (pid, status) in job.procs.iter_mut().filter(|(_, s)| s.is_none()) {
+                match waitpid(*pid, Some(flags)) {
+                    Ok(WaitStatus::Continued(_)) => state = Some(JobState::Running),
+                    Ok(ws) => match wait_status(ws) {
+                        Some(s) if s.stopped_signal().is_some() => state = Some(JobState::Stopped),
+                        Some(s) => *status = Some(s),
+                        None => (),
+                    },
+                    // a subshell can list the shell's jobs, but they aren't
+                    // its children to wait for
+                    Err(_) => (),
+                }
+            }
+            if let Some(state) = state {
+                job.set_state(state);
+            }
+            job.finish();
+        }
+    }
+
+    /// Reports the jobs that finished or stopped since the last prompt
+    pub fn notify_jobs(&mut self) {
+        self.poll_jobs();
+        let changed: Vec<_> = self
+            .jobs
+            .jobs
+            .iter()
+            .filter(|(_, job)| job.changed && job.state != JobState::Running)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in changed {
+            eprintln!("{}", self.jobs.line(id, false));
+            self.forget_if_done(id);
+        }
+    }
+
+    /// Clears a job's change once reported, dropping it if it's done
+    fn forget_if_done(&mut self, id: usize) {
+        let job = self.jobs.jobs.get_mut(&id).unwrap();
+        job.changed = false;
+        if let JobState::Done(_) = job.state {
+            self.jobs.remove(id);
+        }
+    }
+
+    fn job_id(&mut self, builtin: &str, spec: Option<&str>) -> TrshResult<usize> {
+        self.poll_jobs();
+        let spec = spec.unwrap_or("%+");
+        self.jobs.resolve(spec).ok_or_else(|| {
+            let spec = if spec == "%+" { "current" } else { spec };
+            TrshError::gen_exec(builtin, &format!("{spec}: no such job"))
+        })
+    }
+
+    /// `jobs [-lp] [jobspec...]`
+    pub fn exec_jobs(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        let (mut long, mut pids) = (false, false);
+        let mut specs = Vec::new();
+        for arg in args.into_iter().map(CmdArg::into_string) {
+            match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() => {
+                    for c in flags.chars() {
+                        match c {
+                            'l' => long = true,
+                            'p' => pids = true,
+                            c => {
+                                return Err(TrshError::gen_usage(
+                                    "jobs",
+                                    &format!("-{c}: invalid option"),
+                                ));
+                            }
+                        }
+                    }
+                }
+                _ => specs.push(arg),
+            }
+        }
+        self.poll_jobs();
+        let ids = match specs.is_empty() {
+            true => self.jobs.jobs.keys().copied().collect(),
+            false => specs
+                .into_iter()
+                .map(|spec| self.job_id("jobs", Some(&spec)))
+                .collect::<TrshResult<Vec<_>>>()?,
+        };
+        for id in ids {
+            match pids {
+                true => writeln!(io.stdout, "{}", self.jobs.jobs[&id].pgid)?,
+                false => writeln!(io.stdout, "{}", self.jobs.line(id, long))?,
+            }
+            self.forget_if_done(id);
+        }
+        Ok(exit_zero())
+    }
+
+    /// `fg [jobspec]`, continues a job in the foreground and waits for it
+    pub fn exec_fg(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        if !self.job_control {
+            return Err(TrshError::gen_exec("fg", "no job control"));
+        }
+        let spec = args.into_iter().next().map(CmdArg::into_string);
+        let id = self.job_id("fg", spec.as_deref())?;
+        let job = self.jobs.jobs.get_mut(&id).unwrap();
+        writeln!(io.stdout, "{}", job.text)?;
+        io.stdout.flush()?;
+        let _ = tcsetpgrp(io::stdin(), job.pgid);
+        if let Some(tmodes) = &job.tmodes {
+            let _ = tcsetattr(io::stdin(), SetArg::TCSADRAIN, tmodes);
+        }
+        let _ = killpg(job.pgid, Signal::SIGCONT);
+        job.state = JobState::Running;
+        let mut stopped = None;
+        for pid in job.unfinished() {
+            let status = self.wait_pid(pid).unwrap_or(exit_num(1));
+            let job = self.jobs.jobs.get_mut(&id).unwrap();
+            match status.stopped_signal() {
+                Some(_) => stopped = Some(status),
+                None => {
+                    if let Some((_, s)) = job.procs.iter_mut().find(|(p, _)| *p == pid) {
+                        *s = Some(status);
+                    }
+                }
+            }
+        }
+        let job = self.jobs.jobs.get_mut(&id).unwrap();
+        let status = match stopped {
+            Some(status) => {
+                job.tmodes = tcgetattr(io::stdin()).ok();
+                job.state = JobState::Stopped;
+                self.jobs.touch(id);
+                eprintln!();
+                eprintln!("{}", self.jobs.line(id, false));
+                status
+            }
+            None => {
+                let job = self.jobs.remove(id).unwrap();
+                let status = job.procs[job.procs.len() - 1].1.unwrap();
+                if let Some(description) = signal_description(status) {
+                    eprintln!("{description}");
+                }
+                status
+            }
+        };
+        self.take_terminal();
+        self.interrupted(status)
+    }
+
+    /// `bg [jobspec...]`, continues stopped jobs in the background
+    pub fn exec_bg(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        if !self.job_control {
+            return Err(TrshError::gen_exec("bg", "no job control"));
+        }
+        let ids = match args.is_empty() {
+            true => vec![self.job_id("bg", None)?],
+            false => args
+                .into_iter()
+                .map(|spec| self.job_id("bg", Some(&spec.into_string())))
+                .collect::<TrshResult<_>>()?,
+        };
+        for id in ids {
+            let mark = self.jobs.mark(id);
+            let job = self.jobs.jobs.get_mut(&id).unwrap();
+            if job.state == JobState::Running {
+                writeln!(io.stderr, "trsh: bg: job {id} already in background")?;
+                continue;
+            }
+            let _ = killpg(job.pgid, Signal::SIGCONT);
+            job.state = JobState::Running;
+            writeln!(io.stdout, "[{id}]{mark} {} &", job.text)?;
+        }
+        Ok(exit_zero())
+    }
+
+    /// `disown [-ar] [jobspec...]`, drops jobs from the table, `-a` all of
+    /// them and `-r` the running ones
+    pub fn exec_disown(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
+        let args: Vec<String> = args.into_iter().map(CmdArg::into_string).collect();
+        let ids: Vec<usize> = match args.first().map(String::as_str) {
+            Some("-a") => self.jobs.jobs.keys().copied().collect(),
+            Some("-r") => self
+                .jobs
+                .jobs
+                .iter()
+                .filter(|(_, job)| job.state == JobState::Running)
+                .map(|(id, _)| *id)
+                .collect(),
+            None => vec![self.job_id("disown", None)?],
+            Some(_) => args
+                .iter()
+                .map(|spec| self.job_id("disown", Some(spec)))
+                .collect::<TrshResult<_>>()?,
+        };
+        for id in ids {
+            self.jobs.remove(id);
+        }
+        Ok(exit_zero())
+    }
+
+    /// Blocks until `pid` has finished, noting its status in its job
+    fn reap(&mut self, pid: Pid) -> TrshResult<ExitStatus> {
+        if let Some(status) = self.jobs.finished(pid) {
+            return Ok(status);
+        }
+        let status = loop {
+            match waitpid(pid, None).map(wait_status) {
+                Ok(Some(status)) if status.stopped_signal().is_none() => break status,
+                Ok(_) => (),
+                Err(Errno::ECHILD) => {
+                    return Err(TrshError::gen_exec(
+                        "wait",
+                        &format!("pid {pid} is not a child of this shell"),
+                    ));
+                }
+                Err(e) => return Err(io::Error::from(e).into()),
+            }
+        };
+        self.reaped(pid, status);
+        Ok(status)
+    }
+
+    fn reaped(&mut self, pid: Pid, status: ExitStatus) {
+        for job in self.jobs.jobs.values_mut() {
+            if let Some((_, s)) = job.procs.iter_mut().find(|(p, _)| *p == pid) {
+                *s = Some(status);
+                job.finish();
+            }
+        }
+    }
+
+    /// Waits for every process of a job, which leaves the table once waited for
+    fn wait_job(&mut self, id: usize) -> TrshResult<ExitStatus> {
+        for pid in self.jobs.jobs[&id].unfinished() {
+            self.reap(pid)?;
+        }
+        let job = self.jobs.remove(id).unwrap();
+        Ok(job.procs[job.procs.len() - 1].1.unwrap())
+    }
+
+    /// `wait [-n] [pid|jobspec...]`, with no arguments waits for every job.
+    /// The status is the last one waited for's, or with `-n` the first job
+    /// to finish
+    pub fn exec_wait(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
+        let args: Vec<String> = args.into_iter().map(CmdArg::into_string).collect();
+        if args.first().is_some_and(|a| a == "-n") {
+            return self.wait_next();
+        }
+        if args.is_empty() {
+            let ids: Vec<_> = self.jobs.jobs.keys().copied().collect();
+            for id in ids {
+                self.wait_job(id)?;
+            }
+            return Ok(exit_zero());
+        }
+        let mut status = exit_zero();
+        for arg in &args {
+            status = match arg.as_str() {
+                spec if spec.starts_with('%') => {
+                    let id = self.job_id("wait", Some(spec))?;
+                    self.wait_job(id)?
+                }
+                pid => match pid.parse() {
+                    Ok(pid) => match self.reap(Pid::from_raw(pid)) {
+                        Ok(status) => {
+                            self.forget_waited(Pid::from_raw(pid));
+                            status
+                        }
+                        Err(e) => {
+                            eprintln!("trsh: {e}");
+                            exit_num(127)
+                        }
+                    },
+                    Err(_) => {
+                        return Err(TrshError::gen_exec(
+                            "wait",
+                            &format!("`{pid}': not a pid or valid job spec"),
+                        ));
+                    }
+                },
+            };
+        }
+        Ok(status)
+    }
+
+    /// Drops the job `wait pid` finished off, if that was its last process
+    fn forget_waited(&mut self, pid: Pid) {
+        let done = self.jobs.jobs.iter().find(|(_, job)| {
+            matches!(job.state, JobState::Done(_)) && job.procs.iter().any(|(p, _)| *p == pid)
+        });
+        if let Some((&id, _)) = done {
+            self.jobs.remove(id);
+        }
+    }
+
+    /// `wait -n`, 127 when there are no jobs left to wait for
+    fn wait_next(&mut self) -> TrshResult<ExitStatus> {
+        loop {
+            self.poll_jobs();
+            let done = self
+                .jobs
+                .jobs
+                .iter()
+                .find(|(_, job)| matches!(job.state, JobState::Done(_)))
+                .map(|(id, _)| *id);
+            if let Some(id) = done {
+                return self.wait_job(id);
+            }
+            if self
+                .jobs
+                .jobs
+                .values()
+                .all(|job| job.unfinished().is_empty())
+            {
+                return Ok(exit_num(127));
+            }
+            // the only children the shell isn't already waiting on are its
+            // jobs and coprocs
+            match waitpid(Pid::from_raw(-1), None) {
+                Ok(ws) => {
+                    if let (Some(pid), Some(status)) = (ws.pid(), wait_status(ws)) {
+                        self.reaped(pid, status);
+                    }
+                }
+                Err(_) => return Ok(exit_num(127)),
+            }
+        }
+    }
+
+    /// `kill [-s sig | -n num | -sig] pid|jobspec...`, or `kill -l [sig...]`
+    /// to list signals or look one up
+    pub fn exec_kill(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        let args: Vec<String> = args.into_iter().map(CmdArg::into_string).collect();
+        let mut sig = Some(Signal::SIGTERM);
+        let mut targets = &args[..];
+        match targets.first().map(String::as_str) {
+            Some("-l" | "-L") => return list_signals(&args[1..], io),
+            Some("-s" | "-n") => {
+                let name = args.get(1).ok_or(TrshError::gen_usage(
+                    "kill",
+                    &format!("{}: option requires an argument", args[0]),
+                ))?;
+                sig = kill_signal(name)?;
+                targets = &args[2..];
+            }
+            Some("--") => targets = &args[1..],
+            Some(flag) if flag.len() > 1 && flag.starts_with('-') => {
+                sig = kill_signal(&flag[1..])?;
+                targets = &args[1..];
+            }
+            _ => (),
+        }
+        if targets.first().is_some_and(|t| t == "--") {
+            targets = &targets[1..];
+        }
+        if targets.is_empty() {
+            return Err(TrshError::gen_usage(
+                "kill",
+                "usage: kill [-s sigspec | -n signum | -sigspec] pid | jobspec ... or kill -l [sigspec]",
+            ));
+        }
+        let mut status = exit_zero();
+        for target in targets {
+            let sent = match target.as_str() {
+                spec if spec.starts_with('%') => {
+                    let id = self.job_id("kill", Some(spec))?;
+                    let job = &self.jobs.jobs[&id];
+                    let sent = match self.job_control {
+                        true => killpg(job.pgid, sig),
+                        false => job
+                            .unfinished()
+                            .into_iter()
+                            .try_for_each(|pid| kill(pid, sig)),
+                    };
+                    // a stopped job has to run to act on being told to end
+                    if job.state == JobState::Stopped
+                        && matches!(sig, Some(Signal::SIGTERM | Signal::SIGHUP))
+                    {
+                        let _ = killpg(job.pgid, Signal::SIGCONT);
+                    }
+                    sent
+                }
+                pid => match pid.parse() {
+                    Ok(pid) => kill(Pid::from_raw(pid), sig),
+                    Err(_) => {
+                        return Err(TrshError::gen_exec(
+                            "kill",
+                            &format!("{pid}: arguments must be process or job IDs"),
+                        ));
+                    }
+                },
+            };
+            if let Err(e) = sent {
+                writeln!(io.stderr, "trsh: kill: ({target}) - {}", e.desc())?;
+                status = exit_num(1);
+            }
+        }
+        Ok(status)
+    }
+}
+
+/// The signal `kill` sends, `None` for 0 which only checks the process exists
+fn kill_signal(name: &str) -> TrshResult<Option<Signal>> {
+    match name {
+        "0" => Ok(None),
+        name => parse_signal(name).map(Some).ok_or(TrshError::gen_exec(
+            "kill",
+            &format!("{name}: invalid signal specification"),
+        )),
+    }
+}
+
+/// `kill -l`: every signal, or each one named translated to its number and
+/// each number (or status of a command killed by it) to its name
+fn list_signals(args: &[String], io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+    if args.is_empty() {
+        let names: Vec<_> = Signal::iterator()
+            .map(|sig| format!("{:2}) {}", sig as i32, sig.as_str()))
+            .collect();
+        for row in names.chunks(5) {
+            writeln!(io.stdout, "{}", row.join("\t"))?;
+        }
+        return Ok(exit_zero());
+    }
+    for arg in args {
+        let arg = arg.as_str();
+        let sig = match arg.parse::<i32>() {
+            Ok(n) if n > 128 => parse_signal(&(n - 128).to_string()),
+            _ => parse_signal(arg),
+        };
+        let sig = sig.ok_or(TrshError::gen_exec(
+            "kill",
+            &format!("{arg}: invalid signal specification"),
+        ))?;
+        match arg.parse::<i32>() {
+            Ok(_) => writeln!(io.stdout, "{}", &sig.as_str()[3..])?,
+            Err(_) => writeln!(io.stdout, "{}", sig as i32)?,
+        }
+    }
+    Ok(exit_zero())
+}
@@ -0,0 +1,111 @@
+use std::{io::Write, process::ExitStatus};
+
+use crate::{TrshError, TrshResult, ast::CmdArg};
+
+use super::{Executor, builtins::BuiltinIo, utils::exit_zero, vars::VarValue};
+
+/// The options `set -o name` turns on and `set +o name` turns off
+#[derive(Debug, Default)]
+pub struct ShellOptions {
+    /// `set -e`, exit when a command fails outside a condition
+    pub errexit: bool,
+    /// `set -u`, expanding an unset variable is an error
+    pub nounset: bool,
+    /// A pipeline fails with its rightmost failing stage, not just the last
+    pub pipefail: bool,
+}
+
+/// In the order `set -o` lists them
+const OPTION_NAMES: &[&str] = &["errexit", "nounset", "pipefail"];
+
+impl ShellOptions {
+    fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "errexit" => Some(&mut self.errexit),
+            "nounset" => Some(&mut self.nounset),
+            "pipefail" => Some(&mut self.pipefail),
+            _ => None,
+        }
+    }
+
+    fn get(&self, name: &str) -> bool {
+        match name {
+            "errexit" => self.errexit,
+            "nounset" => self.nounset,
+            "pipefail" => self.pipefail,
+            _ => false,
+        }
+    }
+
+    /// The options with a short letter that are on, as `$-` shows them
+    pub fn flags(&self) -> String {
+        [('e', self.errexit), ('u', self.nounset)]
+            .into_iter()
+            .filter_map(|(c, on)| on.then_some(c))
+            .collect()
+    }
+}
+
+impl Executor {
+    /// `set [-eu|+eu] [-o|+o [name]]...`, with no arguments lists the shell's variables
+    pub fn exec_set(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        if args.is_empty() {
+            let mut names: Vec<_> = self.vars.keys().collect();
+            names.sort();
+            for name in names {
+                if let VarValue::Scalar(s) = &self.vars[name].value {
+                    writeln!(io.stdout, "{name}='{s}'")?;
+                }
+            }
+            return Ok(exit_zero());
+        }
+        let mut args = args.into_iter().map(CmdArg::into_string);
+        while let Some(arg) = args.next() {
+            let on = match arg.chars().next() {
+                Some('-') => true,
+                Some('+') => false,
+                _ => {
+                    return Err(TrshError::gen_exec(
+                        "set",
+                        &format!("{arg}: positional parameters aren't supported"),
+                    ));
+                }
+            };
+            for c in arg.chars().skip(1) {
+                match c {
+                    'o' => match args.next() {
+                        Some(name) => {
+                            *self.options.get_mut(&name).ok_or(TrshError::gen_usage(
+                                "set",
+                                &format!("{name}: invalid option name"),
+                            ))? = on;
+                        }
+                        None => self.list_options(on, io)?,
+                    },
+                    'e' => self.options.errexit = on,
+                    'u' => self.options.nounset = on,
+                    c => {
+                        return Err(TrshError::gen_usage(
+                            "set",
+                            &format!("{}{c}: invalid option", if on { '-' } else { '+' }),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(exit_zero())
+    }
+
+    /// `set -o` prints a table, `set +o` the commands that recreate it
+    fn list_options(&self, table: bool, io: &mut BuiltinIo) -> TrshResult<()> {
+        for name in OPTION_NAMES {
+            let on = self.options.get(name);
+            if table {
+                writeln!(io.stdout, "{name:<15}\t{}", if on { "on" } else { "off" })?;
+            } else {
+                writeln!(io.stdout, "set {}o {name}", if on { '-' } else { '+' })?;
+            }
+        }
+        Ok(())
+    }
+}
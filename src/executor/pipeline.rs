@@ -0,0 +1,196 @@
+use std::{
+    io::{self, Write},
+    os::fd::{AsRawFd, OwnedFd, RawFd},
+    process::ExitStatus,
+};
+
+use nix::{
+    sys::{
+        signal::{SigHandler, Signal, signal},
+        wait::{WaitPidFlag, waitpid},
+    },
+    unistd::{ForkResult, Pid, close, dup2, fork},
+};
+
+use crate::{
+    TrshError, TrshResult,
+    ast::{Command, PipeKind},
+    builtins::CmdName,
+};
+
+use super::{
+    Executor, VarValue,
+    jobs::wait_status,
+    utils::{exit_num, status_code},
+};
+
+/// Splits a left-nested `Command::Pipeline` into its stages, along with the
+/// kind of pipe after each one but the last
+fn flatten(cmd: Command, stages: &mut Vec<Command>, pipes: &mut Vec<PipeKind>) {
+    match cmd {
+        Command::Pipeline(left, right, kind) => {
+            flatten(*left, stages, pipes);
+            pipes.push(kind);
+            flatten(*right, stages, pipes);
+        }
+        cmd => stages.push(cmd),
+    }
+}
+
+/// Whether a pipeline's last stage can run in the shell itself, which only
+/// a program can: it gets a process of its own anyway. Builtins, functions
+/// and the rest get a child like every other stage, so `echo | cd /` leaves
+/// the shell where it was
+fn runs_in_shell(cmd: &Command) -> bool {
+    matches!(cmd, Command::Simple(simple) if matches!(simple.name, CmdName::Path(_) | CmdName::Unknown(_)))
+}
+
+impl Executor {
+    /// Starts every stage but the last in a forked child, runs the last one
+    /// here if it's a program, then waits for the rest. Every stage's status goes in
+    /// `PIPESTATUS`, and the pipeline's is the last stage's, or with
+    /// `pipefail` the rightmost failure
+    pub fn exec_pipeline(
+        &mut self,
+        pipeline: Command,
+        stdin: Option<OwnedFd>,
+        stdout: Option<OwnedFd>,
+        stderr: Option<OwnedFd>,
+    ) -> TrshResult<ExitStatus> {
+        let mut stages = Vec::new();
+        let mut pipes = Vec::new();
+        flatten(pipeline, &mut stages, &mut pipes);
+        let last = stages.pop().unwrap();
+        let mut stdin = stdin;
+        let mut input: Option<os_pipe::PipeReader> = None;
+        let mut pids = Vec::new();
+        for (stage, kind) in stages.into_iter().zip(pipes) {
+            let (reader, writer) = os_pipe::pipe()?;
+            let mut fds = vec![(writer.as_raw_fd(), 1)];
+            if kind == PipeKind::Both {
+                fds.push((writer.as_raw_fd(), 2));
+            }
+            if let Some(input) = &input {
+                fds.push((input.as_raw_fd(), 0));
+            }
+            match self.fork_subshell(stage, stdin.take(), &fds, &[reader.as_raw_fd()]) {
+                Ok(pid) => pids.push(pid),
+                Err(e) => {
+                    self.wait_all(pids);
+                    return Err(e);
+                }
+            }
+            input = Some(reader);
+        }
+        let last = match runs_in_shell(&last) {
+            // the pipeline as a whole is what `set -e` looks at
+            true => self
+                .ignoring_errexit(|this| this.exec(last, input.map(OwnedFd::from), stdout, stderr)),
+            false => {
+                let input = input.map(OwnedFd::from);
+                let fds: Vec<_> = [(&input, 0), (&stdout, 1), (&stderr, 2)]
+                    .into_iter()
+                    .filter_map(|(fd, n)| fd.as_ref().map(|fd| (fd.as_raw_fd(), n)))
+                    .collect();
+                let pid = self.fork_subshell(last, None, &fds, &[]);
+                // the earlier stages mustn't be kept writing to a pipe only
+                // the shell still has open
+                drop((input, stdout, stderr));
+                pid.and_then(|pid| self.wait_pid(pid))
+            }
+        };
+        let mut statuses = self.wait_all(pids);
+        statuses.push(last?);
+        self.set_pipestatus(&statuses);
+        let status = match self.options.pipefail {
+            true => statuses.iter().rev().find(|s| !s.success()),
+            false => None,
+        };
+        Ok(status.copied().unwrap_or(statuses[statuses.len() - 1]))
+    }
+
+    pub fn set_pipestatus(&mut self, statuses: &[ExitStatus]) {
+        let codes = statuses
+            .iter()
+            .map(|s| status_code(*s).to_string())
+            .enumerate()
+            .collect();
+        self.put_value("PIPESTATUS", VarValue::Indexed(codes));
+    }
+
+    fn wait_all(&mut self, pids: Vec<Pid>) -> Vec<ExitStatus> {
+        pids.into_iter()
+            .map(|pid| self.wait_pid(pid).unwrap_or(exit_num(1)))
+            .collect()
+    }
+
+    /// Blocks until `pid` exits, or with job control until it stops
+    pub fn wait_pid(&mut self, pid: Pid) -> TrshResult<ExitStatus> {
+        let flags = self.job_control.then_some(WaitPidFlag::WUNTRACED);
+        loop {
+            if let Some(status) = wait_status(waitpid(pid, flags).map_err(io::Error::from)?) {
+                self.job_waited(pid, status);
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Forks a child that runs `cmd` with each `(from, to)` in `fds` moved onto
+    /// `to`, and the fds in `close_fds` (the parent's ends of its pipes) closed. The
+    /// child exits with the command's status
+    pub fn fork_subshell(
+        &mut self,
+        cmd: Command,
+        stdin: Option<OwnedFd>,
+        fds: &[(RawFd, RawFd)],
+        close_fds: &[RawFd],
+    ) -> TrshResult<Pid> {
+        io::stdout().flush()?;
+        // SAFETY: the shell is single threaded, so the child can keep running
+        // the executor after the fork
+        match unsafe { fork() }.map_err(io::Error::from)? {
+            ForkResult::Child => {
+                // SAFETY: restoring the default handler, like any exec'd program
+                // would have, so writing to a closed pipe ends the child quietly
+                let _ = unsafe { signal(Signal::SIGPIPE, SigHandler::SigDfl) };
+                if let Some(setup) = self.child_setup() {
+                    setup.apply();
+                }
+                // a subshell has no job control of its own
+                self.job_control = false;
+                self.starting = None;
+                self.reset_traps();
+                // the coprocs' input is the shell's to close
+                self.coprocs.clear();
+                for fd in close_fds {
+                    let _ = close(*fd);
+                }
+                let redirected = fds
+                    .iter()
+                    .try_for_each(|(from, to)| dup2(*from, *to).map(|_| ()))
+                    .map_err(io::Error::from);
+                for (from, _) in fds {
+                    if fds.iter().all(|(_, to)| to != from) {
+                        let _ = close(*from);
+                    }
+                }
+                let code = match redirected
+                    .map_err(TrshError::from)
+                    .and_then(|_| self.exec(cmd, stdin, None, None))
+                {
+                    Ok(status) => status_code(status),
+                    Err(e) => {
+                        eprintln!("trsh: {e}");
+                        1
+                    }
+                };
+                let _ = io::stdout().flush();
+                std::process::exit(code)
+            }
+            ForkResult::Parent { child } => {
+                self.joined_job(child);
+                Ok(child)
+            }
+        }
+    }
+}
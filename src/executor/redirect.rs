@@ -0,0 +1,214 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    os::fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+};
+
+use nix::{
+    fcntl::{FcntlArg, FdFlag, fcntl},
+    unistd::{close, dup2, pipe},
+};
+
+use crate::{TrshError, TrshResult, ast::Redirection};
+
+use super::{Executor, utils::io_error_message};
+
+/// Where a command's stdin comes from once its redirections are applied
+pub enum Input {
+    /// The shell's own stdin
+    Inherit,
+    Fd(OwnedFd),
+    /// A heredoc's text, fed to the command as it runs
+    Text(String),
+}
+
+impl Input {
+    /// The stream as an fd of its own, a heredoc going through a pipe it has
+    /// to fit in since nothing is left to feed it
+    pub fn into_fd(self) -> io::Result<Option<OwnedFd>> {
+        match self {
+            Input::Inherit => Ok(None),
+            Input::Fd(fd) => Ok(Some(fd)),
+            Input::Text(s) => {
+                let (read, write) = pipe()?;
+                File::from(write).write_all(s.as_bytes())?;
+                Ok(Some(read))
+            }
+        }
+    }
+}
+
+/// Puts `src` at fd `n`, to be kept across exec. An fd that's already there
+/// only loses its close-on-exec flag
+pub fn place_fd(src: RawFd, n: RawFd) -> io::Result<()> {
+    match src == n {
+        true => fcntl(n, FcntlArg::F_SETFD(FdFlag::empty())).map(|_| ()),
+        false => dup2(src, n).map(|_| ()),
+    }
+    .map_err(io::Error::from)
+}
+
+/// A command's streams after its redirections, `None` meaning the shell's own
+pub struct Streams {
+    pub stdin: Input,
+    pub stdout: Option<OwnedFd>,
+    pub stderr: Option<OwnedFd>,
+    /// Redirections of fds past stderr, like `3>log`
+    pub extra: Vec<(RawFd, OwnedFd)>,
+}
+
+impl Streams {
+    /// The fds the redirections replace
+    fn fds(&self) -> Vec<RawFd> {
+        let standard = [
+            !matches!(self.stdin, Input::Inherit),
+            self.stdout.is_some(),
+            self.stderr.is_some(),
+        ];
+        (0..)
+            .zip(standard)
+            .filter_map(|(n, redirected)| redirected.then_some(n))
+            .chain(self.extra.iter().map(|(n, _)| *n))
+            .collect()
+    }
+
+    /// Puts the streams in place of the process's own for good
+    pub fn install(self) -> io::Result<()> {
+        let fds = [
+            (0, self.stdin.into_fd()?),
+            (1, self.stdout),
+            (2, self.stderr),
+        ];
+        let fds = fds
+            .into_iter()
+            .filter_map(|(n, fd)| fd.map(|fd| (n, fd)))
+            .chain(self.extra);
+        for (n, fd) in fds {
+            place_fd(fd.as_raw_fd(), n)?;
+            if fd.as_raw_fd() == n {
+                // it's where it belongs, so it mustn't be closed
+                let _ = fd.into_raw_fd();
+            }
+        }
+        Ok(())
+    }
+
+    /// A copy of what the command's `fd` is redirected to, if it is, so
+    /// `>out 2>&1` sends stderr to `out` as well
+    fn redirected(&self, fd: RawFd) -> Option<TrshResult<OwnedFd>> {
+        let target = match fd {
+            0 => match &self.stdin {
+                Input::Fd(fd) => Some(fd),
+                Input::Inherit | Input::Text(_) => None,
+            },
+            1 => self.stdout.as_ref(),
+            2 => self.stderr.as_ref(),
+            fd => self
+                .extra
+                .iter()
+                .rev()
+                .find(|(n, _)| *n == fd)
+                .map(|(_, f)| f),
+        };
+        target.map(|fd| Ok(fd.try_clone()?))
+    }
+}
+
+impl Executor {
+    /// Runs `f` with the streams in place of the shell's own, then puts those
+    /// back, for builtins like `eval` that run more commands in the shell
+    pub fn with_streams<T>(
+        &mut self,
+        streams: Streams,
+        f: impl FnOnce(&mut Self) -> TrshResult<T>,
+    ) -> TrshResult<T> {
+        let fds = streams.fds();
+        if fds.is_empty() {
+            return f(self);
+        }
+        io::stdout().flush()?;
+        let saved: Vec<_> = fds
+            .into_iter()
+            .map(|n| {
+                let old = fcntl(n, FcntlArg::F_DUPFD_CLOEXEC(10)).ok();
+                // SAFETY: the fcntl just handed us this fd
+                (n, old.map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }))
+            })
+            .collect();
+        let r = streams
+            .install()
+            .map_err(TrshError::from)
+            .and_then(|_| f(self));
+        let _ = io::stdout().flush();
+        for (n, old) in saved {
+            let _ = match old {
+                Some(old) => dup2(old.as_raw_fd(), n).map(|_| ()),
+                // it wasn't open before
+                None => close(n),
+            };
+        }
+        r
+    }
+
+    /// Opens a command's redirections over the streams it was handed by a
+    /// pipeline, later redirections replacing earlier ones
+    pub fn redirect(
+        &self,
+        redirections: Vec<Redirection>,
+        stdin: Option<OwnedFd>,
+        stdout: Option<OwnedFd>,
+        stderr: Option<OwnedFd>,
+    ) -> TrshResult<Streams> {
+        let mut streams = Streams {
+            stdin: stdin.map_or(Input::Inherit, Input::Fd),
+            stdout,
+            stderr,
+            extra: Vec::new(),
+        };
+        for r in redirections {
+            let fd = r.default_fd();
+            let r = match r {
+                Redirection::Fd(_, r) => *r,
+                r => r,
+            };
+            let opened = |s: &str, file: io::Result<File>| {
+                file.map(OwnedFd::from)
+                    .map_err(|e| TrshError::gen_exec(s, &io_error_message(&e)))
+            };
+            let target = match r {
+                Redirection::AppendRight(s) => opened(
+                    &s,
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(self.cwd.join(&s)),
+                )?,
+                Redirection::TruncRight(s) => opened(&s, File::create(self.cwd.join(&s)))?,
+                Redirection::Input(s) => opened(&s, File::open(self.cwd.join(&s)))?,
+                Redirection::DupOut(s) | Redirection::DupIn(s) => {
+                    match s.parse().ok().and_then(|n| streams.redirected(n)) {
+                        Some(fd) => fd?,
+                        None => self.redirect_fd(&s)?,
+                    }
+                }
+                Redirection::HereDoc(s) if fd == 0 => {
+                    streams.stdin = Input::Text(s);
+                    continue;
+                }
+                Redirection::HereDoc(_) | Redirection::Fd(..) => {
+                    return Err(TrshError::gen_exec(
+                        &r.to_string(),
+                        "unsupported redirection",
+                    ));
+                }
+            };
+            match fd {
+                0 => streams.stdin = Input::Fd(target),
+                1 => streams.stdout = Some(target),
+                2 => streams.stderr = Some(target),
+                fd => streams.extra.push((fd, target)),
+            }
+        }
+        Ok(streams)
+    }
+}
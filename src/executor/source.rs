@@ -0,0 +1,157 @@
+use std::{path::PathBuf, process::ExitStatus};
+
+use pest::Parser;
+
+use crate::{
+    ExecError, Program, TrshError, TrshResult,
+    ast::{CmdArg, Command},
+    error_line,
+    prsr::{Rule, TrshPrsr},
+    unparsed_word,
+};
+
+use super::{
+    DEFAULT_PATH, Executor, LoopCtl,
+    redirect::Streams,
+    trap::Trap,
+    utils::{exit_num, exit_zero, io_error_message},
+};
+
+impl Executor {
+    /// `source file [args...]` and `. file [args...]`: runs the file in this
+    /// shell, with `args` as the positional parameters while it runs
+    pub fn exec_source(&mut self, name: &str, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
+        let mut args = args.into_iter().map(CmdArg::into_string);
+        let Some(file) = args.next() else {
+            eprintln!("trsh: {name}: filename argument required");
+            return Ok(exit_num(2));
+        };
+        let script = match std::fs::read_to_string(self.source_path(&file)) {
+            Ok(script) => script,
+            Err(e) => {
+                eprintln!("trsh: {file}: {}", io_error_message(&e));
+                return Ok(exit_num(1));
+            }
+        };
+        let args: Vec<String> = args.collect();
+        let saved = (!args.is_empty()).then(|| {
+            let positional = std::iter::once(self.positional[0].clone()).chain(args);
+            std::mem::replace(&mut self.positional, positional.collect())
+        });
+        self.sourcing += 1;
+        let status = self.run_script(&file, &script);
+        self.sourcing -= 1;
+        if let Some(saved) = saved {
+            self.positional = saved;
+        }
+        let status = match self.loop_ctl {
+            Some(LoopCtl::Return(code)) => {
+                self.loop_ctl = None;
+                exit_num(code)
+            }
+            _ => status?,
+        };
+        self.run_trap(Trap::Return);
+        Ok(status)
+    }
+
+    /// Where `source` finds a file: on PATH if the name has no `/`, and
+    /// otherwise in the current directory
+    fn source_path(&self, file: &str) -> PathBuf {
+        let paths = self.var("PATH").unwrap_or(DEFAULT_PATH);
+        paths
+            .split(':')
+            .filter(|_| !file.contains('/'))
+            .map(|dir| PathBuf::from(dir).join(file))
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| self.cwd.join(file))
+    }
+
+    /// Runs a script's commands one at a time, so an error can say which
+    /// line it came from. Sourced files, `-c` and script files all go through
+    /// here, `file` being what the errors are reported against
+    pub fn run_script(&mut self, file: &str, script: &str) -> TrshResult<ExitStatus> {
+        if script.trim().is_empty() {
+            return Ok(exit_zero());
+        }
+        let program = match TrshPrsr::parse(Rule::program, script) {
+            Ok(mut pairs) => pairs.next().unwrap(),
+            Err(e) => {
+                eprintln!("trsh: {file}: line {}: syntax error", error_line(&e));
+                return Ok(exit_num(2));
+            }
+        };
+        let end = program.as_span().end();
+        let list = program.into_inner().next().unwrap();
+        let mut status = exit_zero();
+        for (line, cmd) in Command::numbered(list, self.env(), &mut None)? {
+            if self.loop_ctl.is_some() {
+                return Ok(status);
+            }
+            status = match self.exec(cmd, None, None, None) {
+                Ok(status) => status,
+                Err(e @ TrshError::Exec(ExecError::Interrupted)) => return Err(e),
+                Err(e) => {
+                    eprintln!("trsh: {file}: line {line}: {e}");
+                    exit_num(1)
+                }
+            };
+        }
+        // the grammar stops at what it can't parse rather than failing
+        let rest = script[end..].trim_start();
+        if let Some(token) = unparsed_word(rest)
+            && self.loop_ctl.is_none()
+        {
+            let line = script[..script.len() - rest.len()].matches('\n').count() + 1;
+            eprintln!("trsh: {file}: line {line}: syntax error near `{token}'");
+            status = exit_num(2);
+        }
+        Ok(status)
+    }
+
+    /// `return [n]`, leaving a sourced file with `n` or the last status
+    pub fn exec_return(&mut self, args: Vec<CmdArg>) -> TrshResult<ExitStatus> {
+        if self.sourcing == 0 {
+            eprintln!("trsh: return: can only `return' from a function or sourced script");
+            return Ok(exit_num(1));
+        }
+        let code = match args.first() {
+            Some(arg) => arg.to_string().parse::<i32>().unwrap_or_else(|_| {
+                eprintln!("trsh: return: {arg}: numeric argument required");
+                2
+            }),
+            None => self.last_status,
+        };
+        self.loop_ctl = Some(LoopCtl::Return(code));
+        Ok(exit_num(code))
+    }
+
+    /// `eval [args...]`: runs the arguments, joined with spaces, as commands
+    /// in this shell
+    pub fn exec_eval(&mut self, args: Vec<CmdArg>, streams: Streams) -> TrshResult<ExitStatus> {
+        let s = args
+            .into_iter()
+            .map(CmdArg::into_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+        if s.trim().is_empty() {
+            return Ok(exit_zero());
+        }
+        self.with_streams(streams, |this| {
+            let program = TrshPrsr::parse(Rule::program, &s)
+                .ok()
+                .and_then(|mut pairs| pairs.next())
+                // the grammar stops at what it can't parse rather than failing
+                .filter(|program| s[program.as_span().end()..].trim().is_empty())
+                .map(|program| Program::new(program, this.env(), &mut None));
+            match program {
+                Some(Ok(Program(cmd))) => this.exec(cmd, None, None, None),
+                Some(Err(TrshError::Ast(_))) | None => {
+                    eprintln!("trsh: eval: syntax error");
+                    Ok(exit_num(2))
+                }
+                Some(Err(e)) => Err(e),
+            }
+        })
+    }
+}
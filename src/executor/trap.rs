@@ -0,0 +1,190 @@
+use std::{
+    io::Write,
+    process::ExitStatus,
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use nix::{
+    libc::c_int,
+    sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal, sigaction},
+};
+
+use crate::{TrshError, TrshResult, ast::CmdArg};
+
+use super::{
+    Executor,
+    builtins::BuiltinIo,
+    jobs::TERMINAL_SIGNALS,
+    utils::{exit_zero, parse_signal},
+};
+
+/// One bit per signal caught since the traps were last run
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+extern "C" fn note_signal(sig: c_int) {
+    PENDING.fetch_or(1 << sig, Ordering::SeqCst);
+}
+
+/// What a trap is set on: a signal, or one of the shell's own events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Trap {
+    /// The shell exiting
+    Exit,
+    /// A command failing, where `set -e` would exit
+    Err,
+    /// Before each simple command
+    Debug,
+    /// A sourced script finishing
+    Return,
+    Signal(Signal),
+}
+
+impl FromStr for Trap {
+    type Err = ();
+
+    /// A pseudo-signal's name, or a signal as `parse_signal` takes it
+    fn from_str(s: &str) -> Result<Self, ()> {
+        Ok(match s.to_ascii_uppercase().as_str() {
+            "EXIT" | "0" => Trap::Exit,
+            "ERR" => Trap::Err,
+            "DEBUG" => Trap::Debug,
+            "RETURN" => Trap::Return,
+            _ => Trap::Signal(parse_signal(s).ok_or(())?),
+        })
+    }
+}
+
+impl Trap {
+    fn name(&self) -> &'static str {
+        match self {
+            Trap::Exit => "EXIT",
+            Trap::Err => "ERR",
+            Trap::Debug => "DEBUG",
+            Trap::Return => "RETURN",
+            Trap::Signal(sig) => sig.as_str(),
+        }
+    }
+}
+
+fn set_handler(sig: Signal, handler: SigHandler) -> TrshResult<()> {
+    let action = SigAction::new(handler, SaFlags::SA_RESTART, SigSet::empty());
+    // SAFETY: `note_signal` only touches an atomic
+    unsafe { sigaction(sig, &action) }
+        .map(|_| ())
+        .map_err(|e| TrshError::gen_exec("trap", &format!("{}: {}", sig.as_str(), e.desc())))
+}
+
+impl Executor {
+    /// `trap [-p] [action] [signal...]`. An empty action ignores the signals,
+    /// `-` (or no action with one signal) puts them back how they were
+    pub fn exec_trap(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        let mut args: Vec<String> = args.into_iter().map(CmdArg::into_string).collect();
+        if args.first().is_some_and(|a| a == "--") {
+            args.remove(0);
+        }
+        match args.first().map(String::as_str) {
+            None => return self.print_traps(&[], io),
+            Some("-p") => return self.print_traps(&args[1..], io),
+            _ => (),
+        }
+        let action = match args.len() {
+            1 => None,
+            _ => Some(args.remove(0)).filter(|a| a != "-"),
+        };
+        for spec in &args {
+            let trap = spec.parse().map_err(|_| {
+                TrshError::gen_exec("trap", &format!("{spec}: invalid signal specification"))
+            })?;
+            if let Trap::Signal(sig) = trap {
+                let handler = match action.as_deref() {
+                    None => self.default_handler(sig),
+                    Some("") => SigHandler::SigIgn,
+                    Some(_) => SigHandler::Handler(note_signal),
+                };
+                set_handler(sig, handler)?;
+            }
+            match &action {
+                Some(action) => self.traps.insert(trap, action.clone()),
+                None => self.traps.remove(&trap),
+            };
+        }
+        Ok(exit_zero())
+    }
+
+    fn print_traps(&self, specs: &[String], io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        let traps = specs
+            .iter()
+            .map(|spec| {
+                spec.parse().map_err(|_| {
+                    TrshError::gen_exec("trap", &format!("{spec}: invalid signal specification"))
+                })
+            })
+            .collect::<TrshResult<Vec<Trap>>>()?;
+        for (trap, action) in &self.traps {
+            if traps.is_empty() || traps.contains(trap) {
+                let action = action.replace('\'', r"'\''");
+                writeln!(io.stdout, "trap -- '{action}' {}", trap.name())?;
+            }
+        }
+        Ok(exit_zero())
+    }
+
+    /// What `trap - SIG` goes back to: ignored for the signals an interactive
+    /// shell ignores itself, the default otherwise
+    fn default_handler(&self, sig: Signal) -> SigHandler {
+        match self.job_control && TERMINAL_SIGNALS.contains(&sig) {
+            true => SigHandler::SigIgn,
+            false => SigHandler::SigDfl,
+        }
+    }
+
+    /// Runs a trap's action if it has one. Traps don't fire while another
+    /// trap is running
+    pub fn run_trap(&mut self, trap: Trap) {
+        if self.in_trap {
+            return;
+        }
+        if let Some(action) = self.traps.get(&trap).cloned() {
+            self.run_action(&action);
+        }
+    }
+
+    /// Runs a trap's action, leaving `$?` as it was
+    pub fn run_action(&mut self, action: &str) {
+        if action.is_empty() {
+            return;
+        }
+        let status = self.last_status;
+        let in_trap = std::mem::replace(&mut self.in_trap, true);
+        if let Err(e) = self.exec_str(action) {
+            eprintln!("trsh: trap: {e}");
+        }
+        self.in_trap = in_trap;
+        self.last_status = status;
+    }
+
+    /// Runs the traps of any signals caught since the last time, called
+    /// between commands where it's safe to
+    pub fn run_pending_traps(&mut self) {
+        if self.in_trap {
+            return;
+        }
+        let pending = PENDING.swap(0, Ordering::SeqCst);
+        for sig in Signal::iterator().filter(|sig| pending & (1 << *sig as i32) != 0) {
+            self.run_trap(Trap::Signal(sig));
+        }
+    }
+
+    /// A subshell doesn't keep the shell's traps, only the signals it ignores
+    pub fn reset_traps(&mut self) {
+        self.traps.retain(|trap, action| match trap {
+            Trap::Signal(sig) if !action.is_empty() => {
+                let _ = set_handler(*sig, SigHandler::SigDfl);
+                false
+            }
+            Trap::Signal(_) => true,
+            _ => false,
+        });
+    }
+}
@@ -1,4 +1,6 @@
-use std::{os::unix::process::ExitStatusExt, path::Path, process::ExitStatus};
+use std::{io, os::unix::process::ExitStatusExt, path::Path, process::ExitStatus, str::FromStr};
+
+use nix::sys::signal::Signal;
 
 use crate::TrshResult;
 
@@ -24,26 +26,25 @@ pub enum BinaryTest {
 
 impl BinaryTest {
     pub fn compare(&self, left: &str, right: &str) -> TrshResult<ExitStatus> {
-        let left_num = match left.parse::<i64>() {
-            Ok(i) => i,
-            Err(_) => return Ok(exit_num(2)),
-        };
-        let right_num = match right.parse::<i64>() {
-            Ok(i) => i,
-            Err(_) => return Ok(exit_num(2)),
-        };
-        if match self {
+        match self.test(left, right) {
+            Some(true) => Ok(exit_zero()),
+            Some(false) => Ok(exit_num(1)),
+            None => Ok(exit_num(2)),
+        }
+    }
+
+    /// Numeric comparison, `None` when either side isn't an integer
+    pub fn test(&self, left: &str, right: &str) -> Option<bool> {
+        let left_num = left.parse::<i64>().ok()?;
+        let right_num = right.parse::<i64>().ok()?;
+        Some(match self {
             BinaryTest::Eq => left_num == right_num,
             BinaryTest::Neq => left_num != right_num,
             BinaryTest::Gt => left_num > right_num,
             BinaryTest::GtEq => left_num >= right_num,
             BinaryTest::Lt => left_num < right_num,
             BinaryTest::LtEq => left_num <= right_num,
-        } {
-            Ok(exit_zero())
-        } else {
-            Ok(exit_num(1))
-        }
+        })
     }
 }
 
@@ -66,6 +67,174 @@ pub fn exit_zero() -> ExitStatus {
     ExitStatus::from_raw(0)
 }
 
+/// A normal exit with code `i`, `from_raw` takes a wait status so the code
+/// goes in the second byte
 pub fn exit_num(i: i32) -> ExitStatus {
-    ExitStatus::from_raw(i)
+    ExitStatus::from_raw((i & 0xff) << 8)
+}
+
+/// The number `$?` reports: the exit code, or 128 plus the signal that
+/// killed or stopped the command
+pub fn status_code(status: ExitStatus) -> i32 {
+    status
+        .code()
+        .unwrap_or_else(|| 128 + status.signal().or(status.stopped_signal()).unwrap_or(0))
+}
+
+/// What bash prints when a command is killed, nothing for SIGINT and SIGPIPE
+/// which are how commands are normally stopped
+pub fn signal_description(status: ExitStatus) -> Option<String> {
+    let sig = status.signal()?;
+    let description = match Signal::try_from(sig) {
+        Ok(Signal::SIGINT | Signal::SIGPIPE) => return None,
+        Ok(Signal::SIGHUP) => "Hangup",
+        Ok(Signal::SIGQUIT) => "Quit",
+        Ok(Signal::SIGILL) => "Illegal instruction",
+        Ok(Signal::SIGTRAP) => "Trace/breakpoint trap",
+        Ok(Signal::SIGABRT) => "Aborted",
+        Ok(Signal::SIGBUS) => "Bus error",
+        Ok(Signal::SIGFPE) => "Floating point exception",
+        Ok(Signal::SIGKILL) => "Killed",
+        Ok(Signal::SIGUSR1) => "User defined signal 1",
+        Ok(Signal::SIGSEGV) => "Segmentation fault",
+        Ok(Signal::SIGUSR2) => "User defined signal 2",
+        Ok(Signal::SIGALRM) => "Alarm clock",
+        Ok(Signal::SIGTERM) => "Terminated",
+        Ok(Signal::SIGXCPU) => "CPU time limit exceeded",
+        Ok(Signal::SIGXFSZ) => "File size limit exceeded",
+        Ok(Signal::SIGSYS) => "Bad system call",
+        _ => return Some(format!("Signal {sig}")),
+    };
+    match status.core_dumped() {
+        true => Some(format!("{description} (core dumped)")),
+        false => Some(description.to_owned()),
+    }
+}
+
+/// A signal by name, with or without `SIG` and in any case, or by number
+pub fn parse_signal(s: &str) -> Option<Signal> {
+    if let Ok(n) = s.parse::<i32>() {
+        return Signal::try_from(n).ok();
+    }
+    let upper = s.to_ascii_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    Signal::from_str(&format!("SIG{name}")).ok()
+}
+
+/// Reads a line from stdin a byte at a time, so input past it is left for
+/// whatever reads stdin next. `None` at end of input
+pub fn read_line_unbuffered() -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        match nix::unistd::read(0, &mut byte)? {
+            0 if line.is_empty() => return Ok(None),
+            0 => break,
+            _ if byte[0] == b'\n' => break,
+            _ => line.push(byte[0]),
+        }
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// An io error's message without the ` (os error N)` std adds
+pub fn io_error_message(e: &io::Error) -> String {
+    let msg = e.to_string();
+    match msg.split_once(" (os error") {
+        Some((msg, _)) => msg.to_owned(),
+        None => msg,
+    }
+}
+
+/// Shell pattern matching as used by `[[ == ]]`: `*`, `?`, `[...]` classes and
+/// `\` escapes, anchored at both ends
+pub fn pattern_match(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+    match_from(&pattern, &s)
+}
+
+/// Escapes the characters `pattern_match` treats specially, for quoted text
+pub fn escape_pattern(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Walks the pattern and string together, remembering only the last `*` seen:
+/// when something fails to match, that star takes one more character and the
+/// match resumes after it. An earlier star never needs to retry, since the
+/// later one can absorb whatever it would have
+fn match_from(p: &[char], s: &[char]) -> bool {
+    let (mut pi, mut si) = (0, 0);
+    let mut star = None;
+    while si < s.len() {
+        if p.get(pi) == Some(&'*') {
+            pi += 1;
+            star = Some((pi, si));
+            continue;
+        }
+        match match_one(&p[pi..], s[si]) {
+            Some(used) => {
+                pi += used;
+                si += 1;
+            }
+            None => match star {
+                Some((after, from)) => {
+                    pi = after;
+                    si = from + 1;
+                    star = Some((after, si));
+                }
+                None => return false,
+            },
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Matches a single character against the start of a pattern that isn't a
+/// `*`, returning how much of the pattern it used
+fn match_one(p: &[char], c: char) -> Option<usize> {
+    match p.first()? {
+        '?' => Some(1),
+        '[' => match match_bracket(&p[1..], Some(c)) {
+            Some((true, used)) => Some(1 + used),
+            Some((false, _)) => None,
+            None => (c == '[').then_some(1),
+        },
+        '\\' if p.len() > 1 => (c == p[1]).then_some(2),
+        lit => (c == *lit).then_some(1),
+    }
+}
+
+/// Matches `c` against the class following a `[`, returning whether it matched
+/// and how much of the pattern the class used, or `None` if the class is unclosed
+fn match_bracket(p: &[char], c: Option<char>) -> Option<(bool, usize)> {
+    let negate = matches!(p.first(), Some('!' | '^'));
+    let mut i = negate as usize;
+    let start = i;
+    let mut found = false;
+    while i < p.len() {
+        let mut lo = p[i];
+        if lo == ']' && i > start {
+            return Some((found != negate, i + 1));
+        }
+        if lo == '\\' && i + 1 < p.len() {
+            i += 1;
+            lo = p[i];
+        }
+        if i + 2 < p.len() && p[i + 1] == '-' && p[i + 2] != ']' {
+            found |= c.is_some_and(|c| lo <= c && c <= p[i + 2]);
+            i += 3;
+        } else {
+            found |= c == Some(lo);
+            i += 1;
+        }
+    }
+    None
 }
@@ -0,0 +1,732 @@
+use std::{collections::BTreeMap, fmt::Display, io::Write, process::ExitStatus};
+
+use crate::{
+    ExecError, TrshError, TrshResult,
+    ast::{AssignValue, Assignment, CmdArg, Token, Word},
+};
+
+use super::{Executor, builtins::BuiltinIo, utils::exit_zero};
+
+/// An entry in the shell's variable table
+#[derive(Debug, Clone)]
+pub struct Var {
+    pub value: VarValue,
+    /// Passed on to child processes
+    pub exported: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum VarValue {
+    Scalar(String),
+    /// Sparse, `arr[10]=x` doesn't fill in 0..10
+    Indexed(BTreeMap<usize, String>),
+    /// `declare -A`
+    Assoc(BTreeMap<String, String>),
+}
+
+impl VarValue {
+    fn values(&self) -> Vec<String> {
+        match self {
+            VarValue::Scalar(s) => vec![s.clone()],
+            VarValue::Indexed(map) => map.values().cloned().collect(),
+            VarValue::Assoc(map) => map.values().cloned().collect(),
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        match self {
+            VarValue::Scalar(_) => vec!["0".to_owned()],
+            VarValue::Indexed(map) => map.keys().map(usize::to_string).collect(),
+            VarValue::Assoc(map) => map.keys().cloned().collect(),
+        }
+    }
+
+    /// One past the highest index, where `+=(...)` starts appending
+    fn next_index(&self) -> usize {
+        match self {
+            VarValue::Scalar(_) => 1,
+            VarValue::Indexed(map) => map.keys().next_back().map_or(0, |k| k + 1),
+            VarValue::Assoc(_) => 0,
+        }
+    }
+}
+
+/// Prints in the form `declare -p` uses
+impl Display for VarValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VarValue::Scalar(s) => write!(f, "\"{s}\""),
+            VarValue::Indexed(map) => {
+                write!(f, "(")?;
+                for (k, v) in map {
+                    write!(f, "[{k}]=\"{v}\" ")?;
+                }
+                write!(f, ")")
+            }
+            VarValue::Assoc(map) => {
+                write!(f, "(")?;
+                for (k, v) in map {
+                    write!(f, "[{k}]=\"{v}\" ")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Splits `name[sub]` into its name and subscript
+/// A name `${...}` can expand: a variable, a positional or a special parameter.
+/// The operators after one (`:-`, `#`, `%` and the rest) aren't supported
+fn is_param_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some('?' | '-' | '!' | '#' | '@' | '*' | '$') => chars.next().is_none(),
+        Some(c) if c.is_ascii_digit() => chars.all(|c| c.is_ascii_digit()),
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn split_subscript(expr: &str) -> (&str, Option<&str>) {
+    match expr.split_once('[') {
+        Some((name, rest)) if rest.ends_with(']') => (name, Some(&rest[..rest.len() - 1])),
+        _ => (expr, None),
+    }
+}
+
+impl Executor {
+    /// Looks up a variable, exported or not. Arrays give their element 0, like bash
+    pub fn var(&self, name: &str) -> Option<&str> {
+        match self.var_value(name)? {
+            VarValue::Scalar(s) => Some(s),
+            VarValue::Indexed(map) => map.get(&0).map(String::as_str),
+            VarValue::Assoc(map) => map.get("0").map(String::as_str),
+        }
+    }
+
+    pub fn var_value(&self, name: &str) -> Option<&VarValue> {
+        self.vars.get(name).map(|v| &v.value)
+    }
+
+    /// Replaces a variable's value, keeping whether it's exported. Assigning
+    /// PATH forgets where commands were found
+    pub fn put_value(&mut self, name: &str, value: VarValue) {
+        if name == "PATH" {
+            self.path_changed();
+        }
+        match self.vars.get_mut(name) {
+            Some(var) => var.value = value,
+            None => {
+                let var = Var {
+                    value,
+                    exported: false,
+                };
+                self.vars.insert(name.to_owned(), var);
+            }
+        }
+    }
+
+    /// Marks a variable for export, creating it empty if it isn't set, or
+    /// unmarks it
+    pub fn set_exported(&mut self, name: &str, exported: bool) {
+        match self.vars.get_mut(name) {
+            Some(var) => var.exported = exported,
+            None if exported => {
+                let var = Var {
+                    value: VarValue::Scalar(String::new()),
+                    exported,
+                };
+                self.vars.insert(name.to_owned(), var);
+            }
+            None => (),
+        }
+    }
+
+    /// The exported variables as `(name, value)`, sorted. Arrays aren't exported
+    pub fn exported(&self) -> Vec<(&str, &str)> {
+        let mut env: Vec<_> = self
+            .vars
+            .iter()
+            .filter(|(_, var)| var.exported)
+            .filter_map(|(name, var)| match &var.value {
+                VarValue::Scalar(s) => Some((name.as_str(), s.as_str())),
+                _ => None,
+            })
+            .collect();
+        env.sort();
+        env
+    }
+
+    /// Expands the inside of `${...}` (or a bare `$name`). `${arr[@]}` gives one
+    /// word per element, and so does `${arr[*]}` unless `quoted`
+    pub fn expand_param(&self, expr: &str, quoted: bool) -> TrshResult<Vec<String>> {
+        // `${!name[@]}` is the only indirection there is
+        let (name, sub) = split_subscript(expr.strip_prefix(['#', '!']).unwrap_or(expr));
+        let supported = is_param_name(expr)
+            || is_param_name(name) && (!expr.starts_with('!') || matches!(sub, Some("@" | "*")));
+        if !supported {
+            return Err(TrshError::gen_exec(
+                &format!("${{{expr}}}"),
+                "bad substitution",
+            ));
+        }
+        if let Some(rest) = expr.strip_prefix('#').filter(|r| !r.is_empty()) {
+            let len = match split_subscript(rest) {
+                (name, Some("@" | "*")) => self.var_value(name).map_or(0, |v| v.values().len()),
+                (name, Some(sub)) => self.element(name, sub)?.map_or(0, |s| s.chars().count()),
+                (name, None) => self.var(name).map_or(0, |s| s.chars().count()),
+            };
+            return Ok(vec![len.to_string()]);
+        }
+        if let Some((name, Some("@" | "*"))) = expr.strip_prefix('!').map(split_subscript) {
+            return Ok(self.var_value(name).map(VarValue::keys).unwrap_or_default());
+        }
+        Ok(match split_subscript(expr) {
+            // the positional parameters, like an array's elements
+            ("*", None) if quoted => vec![self.positional[1..].join(" ")],
+            ("@" | "*", None) => self.positional[1..].to_vec(),
+            (name, Some("*")) if quoted => vec![
+                self.var_value(name)
+                    .map(|v| v.values().join(" "))
+                    .unwrap_or_default(),
+            ],
+            (name, Some("@" | "*")) => self
+                .var_value(name)
+                .map(VarValue::values)
+                .unwrap_or_default(),
+            (name, Some(sub)) => vec![self.bound(expr, self.element(name, sub)?)?],
+            (name, None) => vec![match self.special_param(name) {
+                Some(value) => value,
+                None => self.bound(name, self.var(name).map(str::to_owned))?,
+            }],
+        })
+    }
+
+    /// Unset expands to nothing, or is an error under `set -u`
+    fn bound(&self, name: &str, value: Option<String>) -> TrshResult<String> {
+        match value {
+            Some(value) => Ok(value),
+            None if self.options.nounset => Err(ExecError::Unbound(name.to_owned()).into()),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// `$?` and the other parameters the shell keeps itself
+    fn special_param(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.last_status.to_string()),
+            "-" => Some(self.options.flags()),
+            "!" => Some(
+                self.last_background
+                    .map_or(String::new(), |pid| pid.to_string()),
+            ),
+            "#" => Some((self.positional.len() - 1).to_string()),
+            _ => self
+                .positional
+                .get(name.parse::<usize>().ok()?)
+                .cloned()
+                .or(Some(String::new())),
+        }
+    }
+
+    fn element(&self, name: &str, sub: &str) -> TrshResult<Option<String>> {
+        let Some(value) = self.var_value(name) else {
+            return Ok(None);
+        };
+        Ok(match value {
+            VarValue::Assoc(map) => map.get(&self.subscript(sub)?).cloned(),
+            value => {
+                let i = self.subscript_num(sub)?;
+                let i = if i < 0 {
+                    match usize::try_from(value.next_index() as i64 + i) {
+                        Ok(i) => i,
+                        Err(_) => return Ok(None),
+                    }
+                } else {
+                    i as usize
+                };
+                match value {
+                    VarValue::Indexed(map) => map.get(&i).cloned(),
+                    VarValue::Scalar(s) if i == 0 => Some(s.clone()),
+                    _ => None,
+                }
+            }
+        })
+    }
+
+    /// Expands `$` references in a subscript, dropping surrounding quotes
+    fn subscript(&self, sub: &str) -> TrshResult<String> {
+        let sub = sub.trim();
+        let sub = sub
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .unwrap_or(sub);
+        Ok(self.expand_quoted(sub)?.join(" "))
+    }
+
+    /// An indexed-array subscript: an integer, or a variable holding one
+    fn subscript_num(&self, sub: &str) -> TrshResult<i64> {
+        let sub = self.subscript(sub)?;
+        let sub = sub.trim();
+        Ok(sub
+            .parse()
+            .ok()
+            .or_else(|| self.var(sub).and_then(|v| v.trim().parse().ok()))
+            .unwrap_or(0))
+    }
+
+    /// Expands the `$` references inside a double quoted string, only
+    /// `"${arr[@]}"` and `"$@"` can make more than one word out of it
+    pub fn expand_quoted(&self, s: &str) -> TrshResult<Vec<String>> {
+        let mut words = vec![String::new()];
+        let mut literal = false;
+        let mut chars = s.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let expr = match (c, chars.peek()) {
+                ('\\', Some((_, next @ ('$' | '"' | '\\')))) => {
+                    words.last_mut().unwrap().push(*next);
+                    literal = true;
+                    chars.next();
+                    continue;
+                }
+                ('$', Some((_, '{'))) => match s[i..].find('}') {
+                    Some(end) => {
+                        while chars.next_if(|(j, _)| *j < i + end).is_some() {}
+                        chars.next();
+                        &s[i + 2..i + end]
+                    }
+                    None => &s[i..i + 1],
+                },
+                ('$', Some((_, '?' | '-' | '!' | '#' | '@' | '*'))) => {
+                    chars.next();
+                    &s[i + 1..i + 2]
+                }
+                ('$', Some((_, n))) if n.is_ascii_alphanumeric() || *n == '_' => {
+                    let mut end = s.len();
+                    while let Some((j, n)) = chars.peek() {
+                        if !(n.is_ascii_alphanumeric() || *n == '_') {
+                            end = *j;
+                            break;
+                        }
+                        chars.next();
+                    }
+                    &s[i + 1..end]
+                }
+                (c, _) => {
+                    words.last_mut().unwrap().push(c);
+                    literal = true;
+                    continue;
+                }
+            };
+            if expr == "$" {
+                words.last_mut().unwrap().push('$');
+                literal = true;
+                continue;
+            }
+            let mut values = self.expand_param(expr, true)?.into_iter();
+            if let Some(first) = values.next() {
+                literal = true;
+                words.last_mut().unwrap().push_str(&first);
+            }
+            words.extend(values);
+        }
+        if !literal && words.len() == 1 && words[0].is_empty() && !s.is_empty() {
+            words.clear();
+        }
+        Ok(words)
+    }
+
+    /// Expands a word to a single string, as on the right of an assignment
+    pub fn expand_word(&self, word: &Word) -> TrshResult<String> {
+        word.iter()
+            .map(|t| match t {
+                Token::VarExp(v) => Ok(self.expand_param(v, true)?.join(" ")),
+                Token::Quote(q) => Ok(self.expand_quoted(q)?.join(" ")),
+                t => Ok(t.to_string()),
+            })
+            .collect()
+    }
+
+    /// Expands a word to the separate words it makes, as an array element
+    /// does, the first word of `${arr[@]}` or `"$@"` joining onto what came
+    /// before it and the last onto what follows
+    pub fn expand_fields(&self, word: &Word) -> TrshResult<Vec<String>> {
+        let mut words = vec![String::new()];
+        let mut literal = false;
+        for t in word {
+            let parts = match t {
+                Token::VarExp(v) => {
+                    let parts = self.expand_param(v, false)?;
+                    literal |= parts.iter().any(|p| !p.is_empty());
+                    parts
+                }
+                Token::Quote(q) => {
+                    let parts = self.expand_quoted(q)?;
+                    literal |= !parts.is_empty();
+                    parts
+                }
+                t => {
+                    literal = true;
+                    vec![t.to_string()]
+                }
+            };
+            let mut parts = parts.into_iter();
+            if let Some(first) = parts.next() {
+                words.last_mut().unwrap().push_str(&first);
+            }
+            words.extend(parts);
+        }
+        if !literal {
+            words.clear();
+        }
+        Ok(words)
+    }
+
+    /// Replaces variables and quoted strings with what they expand to
+    pub fn expand_args(&self, args: Vec<CmdArg>) -> TrshResult<Vec<CmdArg>> {
+        let mut expanded = Vec::with_capacity(args.len());
+        for a in args {
+            match a {
+                CmdArg::Variable(v) => {
+                    expanded.extend(self.expand_param(&v, false)?.into_iter().map(CmdArg::Arg))
+                }
+                CmdArg::Quoted(q) => {
+                    expanded.extend(self.expand_quoted(&q)?.into_iter().map(CmdArg::Quoted))
+                }
+                a => expanded.push(a),
+            }
+        }
+        Ok(expanded)
+    }
+
+    pub fn assign(&mut self, assignment: Assignment) -> TrshResult<()> {
+        let Assignment {
+            name,
+            index,
+            append,
+            value,
+        } = assignment;
+        match (index, value) {
+            (None, AssignValue::Word(word)) => {
+                let value = self.expand_word(&word)?;
+                match self.var_value(&name) {
+                    Some(VarValue::Assoc(_) | VarValue::Indexed(_)) => {
+                        self.set_element(&name, "0", value, append)
+                    }
+                    Some(VarValue::Scalar(old)) if append => {
+                        let value = format!("{old}{value}");
+                        self.put_value(&name, VarValue::Scalar(value));
+                        Ok(())
+                    }
+                    _ => {
+                        self.put_value(&name, VarValue::Scalar(value));
+                        Ok(())
+                    }
+                }
+            }
+            (Some(sub), AssignValue::Word(word)) => {
+                let value = self.expand_word(&word)?;
+                self.set_element(&name, &sub, value, append)
+            }
+            (None, AssignValue::Array(elems)) => {
+                let is_assoc = matches!(self.var_value(&name), Some(VarValue::Assoc(_)));
+                let mut array = match self.var_value(&name).cloned() {
+                    Some(value @ (VarValue::Indexed(_) | VarValue::Assoc(_))) if append => value,
+                    Some(VarValue::Scalar(s)) if append => {
+                        VarValue::Indexed(BTreeMap::from([(0, s)]))
+                    }
+                    _ if is_assoc => VarValue::Assoc(BTreeMap::new()),
+                    _ => VarValue::Indexed(BTreeMap::new()),
+                };
+                let mut next = array.next_index();
+                let mut values = Vec::new();
+                for (sub, word) in elems {
+                    match sub {
+                        // a subscripted element is always the one value
+                        Some(sub) => values.push((Some(sub), self.expand_word(&word)?)),
+                        None => values.extend(
+                            self.expand_fields(&word)?
+                                .into_iter()
+                                .map(|value| (None, value)),
+                        ),
+                    }
+                }
+                for (sub, value) in values {
+                    match (&mut array, sub) {
+                        (VarValue::Assoc(map), Some(sub)) => {
+                            map.insert(self.subscript(&sub)?, value);
+                        }
+                        (VarValue::Assoc(_), None) => {
+                            return Err(TrshError::gen_exec(
+                                &name,
+                                &format!(
+                                    "{value}: must use subscript when assigning associative array"
+                                ),
+                            ));
+                        }
+                        (VarValue::Indexed(map), sub) => {
+                            if let Some(sub) = sub {
+                                next =
+                                    usize::try_from(self.subscript_num(&sub)?).map_err(|_| {
+                                        TrshError::gen_exec(
+                                            &name,
+                                            &format!("[{sub}]: bad array subscript"),
+                                        )
+                                    })?;
+                            }
+                            map.insert(next, value);
+                            next += 1;
+                        }
+                        (VarValue::Scalar(_), _) => unreachable!(),
+                    }
+                }
+                self.put_value(&name, array);
+                Ok(())
+            }
+            (Some(_), AssignValue::Array(_)) => Err(TrshError::gen_exec(
+                &name,
+                "cannot assign list to array member",
+            )),
+        }
+    }
+
+    /// Expands the `VAR=value` prefix of a simple command into the values it
+    /// sets for just that command
+    pub fn temp_assignments(
+        &self,
+        assignments: Vec<Assignment>,
+    ) -> TrshResult<Vec<(String, String)>> {
+        assignments
+            .into_iter()
+            .map(|a| match a {
+                Assignment {
+                    name,
+                    index: None,
+                    append,
+                    value: AssignValue::Word(word),
+                } => {
+                    let mut value = self.expand_word(&word)?;
+                    if append {
+                        value.insert_str(0, self.var(&name).unwrap_or_default());
+                    }
+                    Ok((name, value))
+                }
+                Assignment { name, .. } => Err(TrshError::gen_exec(
+                    &name,
+                    "arrays can't be assigned for a single command",
+                )),
+            })
+            .collect()
+    }
+
+    /// Exports `temp` while `f` runs, then puts back whatever was there before
+    pub fn with_temp_vars<T>(
+        &mut self,
+        temp: Vec<(String, String)>,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let path_changed = temp.iter().any(|(name, _)| name == "PATH");
+        if path_changed {
+            self.path_changed();
+        }
+        let saved: Vec<_> = temp
+            .into_iter()
+            .map(|(name, value)| {
+                let var = Var {
+                    value: VarValue::Scalar(value),
+                    exported: true,
+                };
+                let old = self.vars.insert(name.clone(), var);
+                (name, old)
+            })
+            .collect();
+        let r = f(self);
+        for (name, old) in saved.into_iter().rev() {
+            match old {
+                Some(old) => self.vars.insert(name, old),
+                None => self.vars.remove(&name),
+            };
+        }
+        if path_changed {
+            self.path_changed();
+        }
+        r
+    }
+
+    /// Sets a plain scalar without any expansion, for values the shell makes
+    pub fn set_var(&mut self, name: &str, value: String) -> TrshResult<()> {
+        self.assign(Assignment {
+            name: name.to_owned(),
+            index: None,
+            append: false,
+            value: AssignValue::Word(vec![Token::Word(value)]),
+        })
+    }
+
+    fn set_element(
+        &mut self,
+        name: &str,
+        sub: &str,
+        value: String,
+        append: bool,
+    ) -> TrshResult<()> {
+        let array = match self.var_value(name).cloned() {
+            Some(VarValue::Scalar(s)) => VarValue::Indexed(BTreeMap::from([(0, s)])),
+            Some(array) => array,
+            None => VarValue::Indexed(BTreeMap::new()),
+        };
+        let slot = match array {
+            VarValue::Assoc(mut map) => {
+                let key = self.subscript(sub)?;
+                let slot = map.entry(key).or_default();
+                Self::store(slot, value, append);
+                VarValue::Assoc(map)
+            }
+            VarValue::Indexed(mut map) => {
+                let i = self.subscript_num(sub)?;
+                let i = if i < 0 {
+                    map.keys().next_back().map_or(0, |k| k + 1) as i64 + i
+                } else {
+                    i
+                };
+                let i = usize::try_from(i).map_err(|_| {
+                    TrshError::gen_exec(name, &format!("[{sub}]: bad array subscript"))
+                })?;
+                Self::store(map.entry(i).or_default(), value, append);
+                VarValue::Indexed(map)
+            }
+            VarValue::Scalar(_) => unreachable!(),
+        };
+        self.put_value(name, slot);
+        Ok(())
+    }
+
+    fn store(slot: &mut String, value: String, append: bool) {
+        if append {
+            slot.push_str(&value);
+        } else {
+            *slot = value;
+        }
+    }
+
+    /// `unset name` or `unset 'name[sub]'`
+    pub fn unset_var(&mut self, arg: &str) -> TrshResult<()> {
+        match split_subscript(arg) {
+            (name, Some(sub)) => {
+                let key = match self.var_value(name) {
+                    Some(VarValue::Assoc(_)) => Some(self.subscript(sub)?),
+                    Some(VarValue::Indexed(_)) => usize::try_from(self.subscript_num(sub)?)
+                        .ok()
+                        .map(|i| i.to_string()),
+                    _ => None,
+                };
+                match (self.vars.get_mut(name).map(|v| &mut v.value), key) {
+                    (Some(VarValue::Assoc(map)), Some(key)) => {
+                        map.remove(&key);
+                    }
+                    (Some(VarValue::Indexed(map)), Some(key)) => {
+                        map.remove(&key.parse::<usize>().unwrap());
+                    }
+                    _ => (),
+                }
+            }
+            (name, None) => {
+                if name == "PATH" {
+                    self.path_changed();
+                }
+                self.vars.remove(name);
+            }
+        }
+        Ok(())
+    }
+
+    /// `declare [-a|-A|-p|-x] [name[=value]...]`
+    pub fn declare(&mut self, args: Vec<CmdArg>, io: &mut BuiltinIo) -> TrshResult<ExitStatus> {
+        let mut kind = None;
+        let mut print = false;
+        let mut export = false;
+        let mut names = Vec::new();
+        for a in args {
+            match a {
+                CmdArg::Arg(flags) if flags.starts_with('-') => {
+                    for c in flags.chars().skip(1) {
+                        match c {
+                            'a' => kind = Some(VarValue::Indexed(BTreeMap::new())),
+                            'A' => kind = Some(VarValue::Assoc(BTreeMap::new())),
+                            'p' => print = true,
+                            'x' => export = true,
+                            c => {
+                                return Err(TrshError::gen_usage(
+                                    "declare",
+                                    &format!("-{c}: invalid option"),
+                                ));
+                            }
+                        }
+                    }
+                }
+                CmdArg::Declaration(a, _) => names.push((a.name.clone(), Some(a))),
+                CmdArg::Assignment(name, value) => {
+                    let a = Assignment::scalar(&name, value);
+                    names.push((name, Some(a)))
+                }
+                a => names.push((a.to_string(), None)),
+            }
+        }
+        if print || names.is_empty() {
+            let mut listed: Vec<_> = if names.is_empty() {
+                self.vars.keys().cloned().collect()
+            } else {
+                names.into_iter().map(|(n, _)| n).collect()
+            };
+            listed.sort();
+            for name in listed {
+                match self.vars.get(&name) {
+                    Some(Var { value, exported }) => {
+                        let flag = match (value, exported) {
+                            (VarValue::Scalar(_), false) => "--",
+                            (VarValue::Scalar(_), true) => "-x",
+                            (VarValue::Indexed(_), false) => "-a",
+                            (VarValue::Indexed(_), true) => "-ax",
+                            (VarValue::Assoc(_), false) => "-A",
+                            (VarValue::Assoc(_), true) => "-Ax",
+                        };
+                        writeln!(io.stdout, "declare {flag} {name}={value}")?;
+                    }
+                    None => writeln!(io.stderr, "trsh: declare: {name}: not found")?,
+                }
+            }
+            return Ok(exit_zero());
+        }
+        for (name, value) in names {
+            match (&kind, self.var_value(&name)) {
+                (Some(VarValue::Assoc(_)), Some(VarValue::Assoc(_)))
+                | (Some(VarValue::Indexed(_)), Some(VarValue::Indexed(_))) => (),
+                (Some(VarValue::Assoc(_)), Some(VarValue::Indexed(_))) => {
+                    return Err(TrshError::gen_exec(
+                        "declare",
+                        &format!("{name}: cannot convert indexed to associative array"),
+                    ));
+                }
+                (Some(empty), current) => {
+                    let value = match (empty, current) {
+                        (VarValue::Indexed(_), Some(VarValue::Scalar(s))) => {
+                            VarValue::Indexed(BTreeMap::from([(0, s.clone())]))
+                        }
+                        (empty, _) => empty.clone(),
+                    };
+                    self.put_value(&name, value);
+                }
+                (None, _) => (),
+            }
+            if let Some(a) = value {
+                self.assign(a)?;
+            }
+            if export {
+                self.set_exported(&name, true);
+            }
+        }
+        Ok(exit_zero())
+    }
+}
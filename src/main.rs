@@ -5,11 +5,12 @@ mod executor;
 mod prsr;
 use ast::Command;
 use colored::Colorize;
-use executor::Executor;
-use pest::{Parser, iterators::Pair};
+use executor::{Executor, io_error_message};
+use pest::{Parser, error::LineColLocation, iterators::Pair};
 use prsr::{Rule, TrshPrsr};
 use rustyline::{
     Config, Editor,
+    error::ReadlineError,
     history::{DefaultHistory, FileHistory, History},
 };
 use std::{
@@ -17,11 +18,7 @@ use std::{
     fmt::Display,
     path::{Path, PathBuf},
 };
-type ParsedIterResult<'a> =
-    std::result::Result<pest::iterators::Pairs<'a, prsr::Rule>, pest::error::Error<prsr::Rule>>;
-
 type TrshResult<C> = Result<C, TrshError>;
-type ParsedIter<'a> = pest::iterators::Pairs<'a, prsr::Rule>;
 type ParsedPair<'a> = Pair<'a, Rule>;
 
 fn main() {
@@ -34,13 +31,13 @@ fn main() {
             script_file: Some(script_file),
             cmd: None,
         } => {
-            let s = std::fs::read_to_string(script_file).expect("failed to read file");
-            run_once(&s);
+            let s = std::fs::read_to_string(&script_file).expect("failed to read file");
+            run_once(&script_file.display().to_string(), &s);
         }
         CliTrshArgs {
             script_file: None,
             cmd: Some(cmd),
-        } => run_once(&cmd),
+        } => run_once("-c", &cmd),
         _ => panic!("got weird input"),
     }
 }
@@ -52,8 +49,11 @@ fn repl() {
     history.load(h).unwrap();
     let mut rl: Editor<(), FileHistory> = Editor::with_history(config, history).unwrap();
     let mut executor = Executor::new();
+    executor.enable_job_control();
     executor.load_trshrc();
     loop {
+        executor.run_pending_traps();
+        executor.notify_jobs();
         let prompt = format!("{}{}{} ", "[trsh: ".cyan(), executor, "]$".cyan());
         match rl.readline(&prompt) {
             Ok(readline) => {
@@ -61,28 +61,67 @@ fn repl() {
                     // .inspect(|e| println!("{:?}", e))
                     .map_err(|e| TrshError::Pest(Box::new(e)))
                     .and_then(|mut r| {
-                        Program::new(r.next().unwrap(), executor.env(), &mut Some(&mut rl))
+                        let program = r.next().unwrap();
+                        // the grammar stops at what it can't parse rather than failing
+                        let rest = &readline[program.as_span().end()..];
+                        match unparsed_word(rest) {
+                            Some(token) => Err(TrshError::Syntax(token.to_owned())),
+                            None => Program::new(program, executor.env(), &mut Some(&mut rl)),
+                        }
                     })
-                    .and_then(|prog| executor.exec(prog.0, None, None))
+                    .and_then(|prog| executor.exec(prog.0, None, None, None))
                     .map(|_| {})
-                    .map_err(|e| eprintln!("trsh: full bubble {e:?}"))
+                    .map_err(|e| match e {
+                        TrshError::Exec(ExecError::Interrupted) => eprintln!(),
+                        TrshError::Pest(e) => {
+                            eprintln!("trsh: line {}: syntax error", error_line(&e))
+                        }
+                        TrshError::Syntax(token) => {
+                            eprintln!("trsh: syntax error near `{token}'")
+                        }
+                        e => eprintln!("trsh: {e}"),
+                    })
                     .ok();
             }
-            Err(readerr) => panic!("{readerr}"),
+            // Ctrl-C at the prompt just drops the line
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => {
+                eprintln!("exit");
+                executor.exit(executor.last_status())
+            }
+            Err(readerr) => {
+                eprintln!("trsh: {readerr}");
+                executor.exit(1)
+            }
         }
     }
 }
 
-fn run_once(s: &str) {
+fn run_once(name: &str, s: &str) {
     let mut executor = Executor::new();
-    TrshPrsr::parse(Rule::program, s)
-        // .inspect(|e| println!("{:?}", e))
-        .map_err(|e| TrshError::Pest(Box::new(e)))
-        .and_then(|mut r| Program::new(r.next().unwrap(), executor.env(), &mut None))
-        .and_then(|prog| executor.exec(prog.0, None, None))
-        .map(|_| {})
-        .map_err(|e| eprintln!("{e:?}"))
-        .ok();
+    match executor.run_script(name, s) {
+        // a command killed by a signal has no code, but `$?` has its 128+n
+        Ok(status) => executor.exit(status.code().unwrap_or(executor.last_status())),
+        Err(e) => {
+            eprintln!("trsh: {name}: {e}");
+            executor.exit(executor.last_status())
+        }
+    }
+}
+
+/// The word to report a syntax error near, in text the grammar stopped
+/// short of. The separator it stopped at isn't the problem
+fn unparsed_word(rest: &str) -> Option<&str> {
+    let separator = |c: char| c.is_whitespace() || matches!(c, ';' | '&' | '|');
+    rest.split(separator)
+        .find(|word| !word.is_empty())
+        .or(rest.split_whitespace().next())
+}
+
+/// The line a parse failed on
+fn error_line(e: &pest::error::Error<Rule>) -> usize {
+    let (LineColLocation::Pos((line, _)) | LineColLocation::Span((line, _), _)) = e.line_col;
+    line
 }
 
 #[derive(Debug)]
@@ -103,12 +142,36 @@ enum TrshError {
     Ast(AstError),
     Exec(ExecError),
     Pest(Box<pest::error::Error<prsr::Rule>>),
+    /// Text left over after what the grammar could parse, from its first word
+    Syntax(String),
 }
 
 impl TrshError {
     pub fn gen_exec(name: &str, expl: &str) -> Self {
         Self::Exec(ExecError::new(name, expl))
     }
+
+    /// A builtin given options or arguments it doesn't take, which fails
+    /// with status 2 rather than 1
+    pub fn gen_usage(name: &str, expl: &str) -> Self {
+        Self::Exec(ExecError::Usage(Box::new(Expl {
+            name: name.to_owned(),
+            expl: expl.to_owned(),
+        })))
+    }
+}
+
+impl Display for TrshError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ast(AstError::IncompleteConditional) => {
+                write!(f, "incomplete conditional expression")
+            }
+            Self::Exec(e) => write!(f, "{e}"),
+            Self::Pest(e) => write!(f, "line {}: syntax error", error_line(e)),
+            Self::Syntax(token) => write!(f, "syntax error near `{token}'"),
+        }
+    }
 }
 
 impl From<std::io::Error> for TrshError {
@@ -134,9 +197,12 @@ enum AstError {
 }
 #[derive(Debug)]
 enum ExecError {
-    Failed,
-    UnknownCmd,
+    /// A foreground job was killed by Ctrl-C, abandoning the command line
+    Interrupted,
     General(Box<Expl>),
+    Usage(Box<Expl>),
+    /// A variable expanded under `set -u` without being set
+    Unbound(String),
     IO(Box<std::io::Error>),
 }
 
@@ -149,6 +215,17 @@ impl ExecError {
     }
 }
 
+impl Display for ExecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Interrupted => write!(f, "interrupted"),
+            Self::General(expl) | Self::Usage(expl) => write!(f, "{expl}"),
+            Self::Unbound(name) => write!(f, "{name}: unbound variable"),
+            Self::IO(e) => write!(f, "{}", io_error_message(e)),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Expl {
     name: String,
@@ -0,0 +1,39 @@
+mod common;
+
+use common::trsh;
+
+#[test]
+fn let_assigns_several() {
+    assert_eq!(trsh("let x=5 y=2; echo $x $y").stdout, "5 2\n");
+}
+
+#[test]
+fn let_keeps_running_the_line() {
+    assert_eq!(trsh("let y=3*2; echo reached $y").stdout, "reached 6\n");
+}
+
+#[test]
+fn let_increments_and_compound_assigns() {
+    assert_eq!(trsh("x=1; let x++; let x+=4; echo $x").stdout, "6\n");
+}
+
+#[test]
+fn let_quoted_expression() {
+    assert_eq!(trsh("let \"z = 2 + 3\"; echo $z").stdout, "5\n");
+}
+
+#[test]
+fn let_status_follows_the_value() {
+    assert_eq!(trsh("let 0; echo $?; let 2; echo $?").stdout, "1\n0\n");
+}
+
+#[test]
+fn let_prefixed_name_is_not_let() {
+    assert_eq!(trsh("letx=1; echo $letx").stdout, "1\n");
+}
+
+#[test]
+fn arith_command_and_for() {
+    let run = trsh("for ((i = 0; i < 3; i++)) do echo $i; done; (( i == 3 )); echo $?");
+    assert_eq!(run.stdout, "0\n1\n2\n0\n");
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::trsh;
+
+#[test]
+fn builtin_and_command_nest() {
+    let run = trsh("builtin command echo x; command builtin echo y");
+    assert_eq!(run.stdout, "x\ny\n");
+}
+
+#[test]
+fn builtin_rejects_non_builtin() {
+    let run = trsh("builtin ls; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(run.stderr, "trsh: builtin: ls: not a shell builtin\n");
+}
+
+#[test]
+fn type_describes_builtin_and_keyword() {
+    let run = trsh("type cd if; type -t echo");
+    assert_eq!(
+        run.stdout,
+        "cd is a shell builtin\nif is a shell keyword\nbuiltin\n"
+    );
+}
+
+#[test]
+fn command_invalid_option() {
+    let run = trsh("command -z echo; echo $?");
+    assert_eq!(run.stdout, "2\n");
+    assert_eq!(run.stderr, "trsh: command: -z: invalid option\n");
+}
+
+#[test]
+fn hash_remembers_path() {
+    let run = trsh("hash; hash ls; hash -t ls");
+    let (empty, path) = run.stdout.split_once('\n').unwrap();
+    assert_eq!(empty, "hash: hash table empty");
+    assert!(path.trim_end().ends_with("/ls"), "{path:?}");
+}
@@ -0,0 +1,97 @@
+// each test file only uses some of these
+#![allow(dead_code)]
+
+use std::{
+    io::Write,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What a run of the shell printed and how it exited
+pub struct Run {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>,
+}
+
+/// A directory for a test to make files in, removed again when dropped
+pub struct Scratch(PathBuf);
+
+impl Deref for Scratch {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A fresh scratch directory
+pub fn scratch(name: &str) -> Scratch {
+    let dir = std::env::temp_dir().join(format!("trsh-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    Scratch(dir)
+}
+
+/// Runs `script` with `trsh -c` in the temp directory
+pub fn trsh(script: &str) -> Run {
+    trsh_in(&std::env::temp_dir(), script)
+}
+
+/// Runs `script` with `trsh -c` in `dir`, with stdin at EOF
+pub fn trsh_in(dir: &Path, script: &str) -> Run {
+    run(dir, script, None)
+}
+
+/// Runs `script` with `trsh -c` in the temp directory, reading `input`
+pub fn trsh_input(script: &str, input: &str) -> Run {
+    run(&std::env::temp_dir(), script, Some(input))
+}
+
+/// A shell that hangs is killed after ten seconds and fails the test
+fn run(dir: &Path, script: &str, input: Option<&str>) -> Run {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_trsh"))
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir)
+        .stdin(match input {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    if let Some(input) = input {
+        // dropped after writing, so the shell sees the end of it
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+    }
+    let start = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if start.elapsed() > Duration::from_secs(10) {
+            let _ = child.kill();
+            panic!("trsh -c {script:?} didn't finish in 10s");
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let output = child.wait_with_output().unwrap();
+    Run {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        code: output.status.code(),
+    }
+}
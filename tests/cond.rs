@@ -0,0 +1,50 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use common::trsh;
+
+fn matches(test: &str) -> bool {
+    let run = trsh(&format!("[[ {test} ]]; echo $?"));
+    match run.stdout.trim() {
+        "0" => true,
+        "1" => false,
+        out => panic!("`{test}` printed {out:?}, stderr {:?}", run.stderr),
+    }
+}
+
+#[test]
+fn glob_patterns() {
+    assert!(matches("abc == a*"));
+    assert!(matches("abc == *c"));
+    assert!(matches("abc == a?c"));
+    assert!(matches("abc == a[a-c]c"));
+    assert!(matches("abc == a[!x]c"));
+    assert!(matches("abc == **"));
+    assert!(matches("axbxc == a*b*c"));
+    assert!(!matches("abc == a*d"));
+    assert!(!matches("abc == ab"));
+    assert!(!matches("abc == a[!b]c"));
+    assert!(matches("abc != a*d"));
+}
+
+#[test]
+fn quoted_pattern_is_literal() {
+    assert!(matches(r#"'a*' == "a*""#));
+    assert!(!matches(r#"abc == "a*""#));
+}
+
+#[test]
+fn many_stars_dont_backtrack_forever() {
+    let start = Instant::now();
+    assert!(!matches(
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaab == *a*a*a*a*a*a*a*a*a*a*a*c"
+    ));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn regex_sets_bash_rematch() {
+    let run = trsh("[[ ab12 =~ ([a-z]+)([0-9]+) ]]; echo $? ${BASH_REMATCH[1]} ${BASH_REMATCH[2]}");
+    assert_eq!(run.stdout, "0 ab 12\n");
+}
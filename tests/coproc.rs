@@ -0,0 +1,18 @@
+mod common;
+
+use common::trsh;
+
+#[test]
+fn coproc_fds_stay_in_the_shell() {
+    // the second coproc's subshell and `ls` get no copy of the first's pipes
+    let run =
+        trsh("coproc C { cat; }; coproc D { sleep 0.3; }; ls \"/proc/$D_PID/fd\"; ls /proc/self/fd");
+    assert_eq!(run.stdout, "0\n1\n2\n0\n1\n2\n3\n");
+}
+
+#[test]
+fn finished_coproc_is_unset() {
+    let run =
+        trsh("coproc C { sleep 0.1; }; echo ${#C[@]}; sleep 0.4; echo \"[${C[@]}] [$C_PID]\"");
+    assert_eq!(run.stdout, "2\n[] []\n");
+}
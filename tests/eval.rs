@@ -0,0 +1,39 @@
+mod common;
+
+use common::{scratch, trsh, trsh_in};
+
+#[test]
+fn eval_runs_joined_args() {
+    let run = trsh("eval 'x=1;' 'echo $x'; echo $x");
+    assert_eq!(run.stdout, "1\n1\n");
+}
+
+#[test]
+fn eval_syntax_error_carries_on() {
+    let run = trsh(r#"eval "if [[ ; then"; echo $?"#);
+    assert_eq!(run.stdout, "2\n");
+    assert_eq!(run.stderr, "trsh: eval: syntax error\n");
+    assert_eq!(run.code, Some(0));
+}
+
+#[test]
+fn eval_trailing_syntax_error_runs_nothing() {
+    let run = trsh(r#"eval "echo a; if [[ ; then"; echo $?"#);
+    assert_eq!(run.stdout, "2\n");
+    assert_eq!(run.stderr, "trsh: eval: syntax error\n");
+}
+
+#[test]
+fn eval_output_is_redirected() {
+    let dir = scratch("eval-redirect");
+    let run = trsh_in(&dir, "eval 'echo a; echo b' > out; cat out");
+    assert_eq!(run.stdout, "a\nb\n");
+}
+
+#[test]
+fn eval_error_is_redirected() {
+    let dir = scratch("eval-error-redirect");
+    let run = trsh_in(&dir, r#"eval "if [[ ; then" 2> err; cat err"#);
+    assert_eq!(run.stdout, "trsh: eval: syntax error\n");
+    assert_eq!(run.stderr, "");
+}
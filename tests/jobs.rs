@@ -0,0 +1,73 @@
+mod common;
+
+use common::trsh;
+
+#[test]
+fn jobspec_that_looks_like_an_assignment() {
+    let run = trsh("jobs a=b; echo $?; disown a=b; echo $?");
+    assert_eq!(run.stdout, "1\n1\n");
+    assert_eq!(
+        run.stderr,
+        "trsh: jobs: a=b: no such job\ntrsh: disown: a=b: no such job\n"
+    );
+}
+
+#[test]
+fn fg_without_job_control() {
+    let run = trsh("fg a=b; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(run.stderr, "trsh: fg: no job control\n");
+}
+
+#[test]
+fn jobs_lists_background_job() {
+    let run = trsh("sleep 1 & jobs");
+    assert!(run.stdout.contains("sleep 1"), "{:?}", run.stdout);
+}
+
+#[test]
+fn kill_assignment_shaped_target() {
+    let run = trsh("kill a=b; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(
+        run.stderr,
+        "trsh: kill: a=b: arguments must be process or job IDs\n"
+    );
+}
+
+#[test]
+fn wait_assignment_shaped_target() {
+    let run = trsh("wait a=b; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert!(run.stderr.contains("a=b"), "{:?}", run.stderr);
+}
+
+#[test]
+fn kill_lists_signal_names() {
+    let run = trsh("kill -l 9 TERM");
+    assert_eq!(run.stdout, "KILL\n15\n");
+}
+
+#[test]
+fn kill_background_job() {
+    let run = trsh("sleep 5 & kill %1; wait %1; echo $?");
+    assert_eq!(run.stdout, "143\n");
+}
+
+#[test]
+fn wait_for_non_child() {
+    let run = trsh("wait 99999; echo $?");
+    assert_eq!(run.stdout, "127\n");
+    assert_eq!(
+        run.stderr,
+        "trsh: wait: pid 99999 is not a child of this shell\n"
+    );
+}
+
+#[test]
+fn wait_after_jobs_reported_done() {
+    let run =
+        trsh("sh -c 'exit 7' & sleep 0.3; jobs > /dev/null; wait $!; echo $?; wait $!; echo $?");
+    assert_eq!(run.stdout, "7\n7\n");
+    assert_eq!(run.stderr, "");
+}
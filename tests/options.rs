@@ -0,0 +1,38 @@
+mod common;
+
+use common::trsh;
+
+#[test]
+fn nounset_stops_the_script() {
+    let run = trsh("set -u; echo $NOPE; echo after");
+    assert_eq!(run.stdout, "");
+    assert_eq!(run.stderr, "trsh: NOPE: unbound variable\n");
+    assert_eq!(run.code, Some(1));
+}
+
+#[test]
+fn nounset_in_braces() {
+    let run = trsh("set -u; echo ${NOPE}x");
+    assert_eq!(run.stderr, "trsh: NOPE: unbound variable\n");
+    assert_eq!(run.code, Some(1));
+}
+
+#[test]
+fn nounset_only_ends_a_pipeline_stage() {
+    let run = trsh("set -u; echo $NOPE | cat; echo after");
+    assert_eq!(run.stdout, "after\n");
+}
+
+#[test]
+fn unset_expands_to_nothing_by_default() {
+    let run = trsh(r#"x=$NOPE; echo "[$x]""#);
+    assert_eq!(run.stdout, "[]\n");
+    assert_eq!(run.stderr, "");
+}
+
+#[test]
+fn errexit_stops_at_failure() {
+    let run = trsh("set -e; false; echo reached");
+    assert_eq!(run.stdout, "");
+    assert_eq!(run.code, Some(1));
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::trsh;
+
+#[test]
+fn pipe_and_carries_stderr() {
+    let run = trsh("ls nonexistent |& wc -l");
+    assert_eq!(run.stdout.trim(), "1");
+    assert_eq!(run.stderr, "");
+}
+
+#[test]
+fn plain_pipe_leaves_stderr() {
+    let run = trsh("ls nonexistent | wc -l");
+    assert_eq!(run.stdout.trim(), "0");
+    assert!(run.stderr.contains("nonexistent"));
+}
+
+#[test]
+fn pipe_into_if() {
+    let run = trsh("echo piped | if true; then cat; fi");
+    assert_eq!(run.stdout, "piped\n");
+}
+
+#[test]
+fn pipe_into_while() {
+    let run = trsh("echo piped | while true; do cat; break; done");
+    assert_eq!(run.stdout, "piped\n");
+}
+
+#[test]
+fn pipe_into_select() {
+    let run = trsh("echo 2 | select x in a b; do echo $x; break; done");
+    assert_eq!(run.stdout, "b\n");
+}
+
+#[test]
+fn compound_last_stage_status() {
+    let run = trsh(
+        "echo x | if cat; then false; fi; echo $?; echo x | while cat; do break; done; echo $?",
+    );
+    assert_eq!(run.stdout, "x\n1\nx\n0\n");
+}
+
+#[test]
+fn pipefail_and_pipestatus() {
+    let run = trsh(
+        "false | true; echo $?; set -o pipefail; false | true; echo $?; true | false | true; echo ${PIPESTATUS[1]}",
+    );
+    assert_eq!(run.stdout, "0\n1\n1\n");
+}
+
+#[test]
+fn negated_pipeline() {
+    let run = trsh("! true; echo $?; ! false | false; echo $?");
+    assert_eq!(run.stdout, "1\n0\n");
+}
+
+#[test]
+fn last_builtin_stage_runs_in_subshell() {
+    let run = trsh("cd /tmp; echo | cd /; pwd; x=1; echo 1 | x=5; echo $x");
+    assert_eq!(run.stdout, "/tmp\n1\n");
+}
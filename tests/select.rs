@@ -0,0 +1,31 @@
+mod common;
+
+use common::trsh_input;
+
+#[test]
+fn select_sets_choice_and_reply() {
+    let run = trsh_input("select x in a b; do echo $x $REPLY; break; done", "2\n");
+    assert_eq!(run.stdout, "b 2\n");
+    assert_eq!(run.stderr, "1) a\n2) b\n#? ");
+}
+
+#[test]
+fn select_leaves_later_input_for_body() {
+    let run = trsh_input("select x in a; do head -1; break; done", "1\nline2\n");
+    assert_eq!(run.stdout, "line2\n");
+}
+
+#[test]
+fn nested_select() {
+    let run = trsh_input(
+        "select x in a b; do select y in c d; do echo $x $y; break; done; break; done",
+        "1\n2\n",
+    );
+    assert_eq!(run.stdout, "a d\n");
+}
+
+#[test]
+fn select_ends_at_eof() {
+    let run = trsh_input("select x in a; do echo $x; done; echo done", "1\n");
+    assert_eq!(run.stdout, "a\ndone\n");
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use std::fs;
+
+use common::{scratch, trsh_in};
+
+#[test]
+fn source_output_is_redirected() {
+    let dir = scratch("source-redirect");
+    fs::write(dir.join("f"), "echo from file\n").unwrap();
+    let run = trsh_in(&dir, "source f > out; . f >> out; cat out");
+    assert_eq!(run.stdout, "from file\nfrom file\n");
+}
+
+#[test]
+fn source_with_positional_args() {
+    let dir = scratch("source-args");
+    fs::write(
+        dir.join("f"),
+        "echo $# $1\nprintf '%s|' \"$@\"; echo\nprintf '%s|' \"$*\"; echo\n",
+    )
+    .unwrap();
+    let run = trsh_in(&dir, "source f 'a b' c; echo $#");
+    assert_eq!(run.stdout, "2 a b\na b|c|\na b c|\n0\n");
+}
+
+#[test]
+fn return_leaves_sourced_file() {
+    let dir = scratch("source-return");
+    fs::write(dir.join("f"), "echo before\nreturn 3\necho after\n").unwrap();
+    let run = trsh_in(&dir, ". f; echo $?");
+    assert_eq!(run.stdout, "before\n3\n");
+}
+
+#[test]
+fn missing_sourced_file() {
+    let dir = scratch("source-missing");
+    let run = trsh_in(&dir, "source nofile; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(run.stderr, "trsh: nofile: No such file or directory\n");
+}
+
+#[test]
+fn source_by_path() {
+    let dir = scratch("source-path");
+    fs::write(dir.join("s.sh"), "echo sourced\n").unwrap();
+    let script = format!(". ./s.sh; source {}", dir.join("s.sh").display());
+    let run = trsh_in(&dir, &script);
+    assert_eq!(run.stdout, "sourced\nsourced\n");
+    assert_eq!(run.stderr, "");
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::{scratch, trsh, trsh_in};
+
+#[test]
+fn failing_builtin_sets_status() {
+    let run = trsh("cd nonexist; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(
+        run.stderr,
+        "trsh: cd: nonexist: No such file or directory\n"
+    );
+    assert_eq!(run.code, Some(0));
+}
+
+#[test]
+fn invalid_option_is_status_2() {
+    let run = trsh("hash -z; echo $?");
+    assert_eq!(run.stdout, "2\n");
+    assert_eq!(run.stderr, "trsh: hash: -z: invalid option\n");
+}
+
+#[test]
+fn missing_input_file() {
+    let dir = scratch("missing-input");
+    let run = trsh_in(&dir, "cat < nofile; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(run.stderr, "trsh: nofile: No such file or directory\n");
+}
+
+#[test]
+fn output_into_missing_dir() {
+    let dir = scratch("missing-dir");
+    let run = trsh_in(&dir, "echo hi > nodir/x; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(run.stderr, "trsh: nodir/x: No such file or directory\n");
+}
+
+#[test]
+fn failed_builtin_takes_or_branch() {
+    let run = trsh("cd nonexist && echo and || echo or");
+    assert_eq!(run.stdout, "or\n");
+}
+
+#[test]
+fn errexit_stops_at_failed_builtin() {
+    let run = trsh("set -e; cd nonexist; echo reached");
+    assert_eq!(run.stdout, "");
+    assert_eq!(run.code, Some(1));
+}
+
+#[test]
+fn syntax_error_exits_2() {
+    let run = trsh("if [[ ; then");
+    assert_eq!(run.stderr, "trsh: -c: line 1: syntax error\n");
+    assert_eq!(run.code, Some(2));
+}
+
+#[test]
+fn unknown_command_is_127() {
+    let run = trsh("nosuchcmd; echo $?");
+    assert_eq!(run.stdout, "127\n");
+    assert_eq!(run.stderr, "trsh: nosuchcmd: command not found\n");
+}
+
+#[test]
+fn unparsed_rest_is_a_syntax_error() {
+    let run = trsh("echo a; fi; echo b");
+    assert_eq!(run.stdout, "a\n");
+    assert_eq!(run.stderr, "trsh: -c: line 1: syntax error near `fi'\n");
+    assert_eq!(run.code, Some(2));
+}
+
+#[test]
+fn failed_test_is_status_1() {
+    let run = trsh("test -z a; echo $?; alias; echo $?");
+    assert_eq!(run.stdout, "1\n0\n");
+}
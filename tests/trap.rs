@@ -0,0 +1,15 @@
+mod common;
+
+use common::trsh;
+
+#[test]
+fn exit_trap_runs_last() {
+    let run = trsh("trap 'echo bye' EXIT; echo hi");
+    assert_eq!(run.stdout, "hi\nbye\n");
+}
+
+#[test]
+fn err_trap_runs_on_failure() {
+    let run = trsh("trap 'echo err' ERR; false; true; echo after");
+    assert_eq!(run.stdout, "err\nafter\n");
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::trsh;
+
+#[test]
+fn indexed_and_assoc_arrays() {
+    let run = trsh("a=(x y z); echo ${a[1]} ${#a[@]}; declare -A m; m[k]=v; echo ${m[k]}");
+    assert_eq!(run.stdout, "y 3\nv\n");
+}
+
+#[test]
+fn prefix_assignment_is_scoped() {
+    let run = trsh("FOO=bar printenv FOO; echo \"<$FOO>\"");
+    assert_eq!(run.stdout, "bar\n<>\n");
+}
+
+#[test]
+fn only_exported_vars_reach_children() {
+    let run = trsh("x=1; printenv x; echo $?; export x; printenv x");
+    assert_eq!(run.stdout, "1\n1\n");
+}
+
+#[test]
+fn array_literal_splits_expansions() {
+    let run = trsh(
+        r#"a=(x y); b=("${a[@]}"); echo ${#b[@]}; c=(${a[@]} z); echo ${#c[@]}; d=("${a[*]}"); echo ${#d[@]}"#,
+    );
+    assert_eq!(run.stdout, "2\n3\n1\n");
+}
+
+#[test]
+fn array_literal_joins_around_expansion() {
+    let run = trsh(r#"a=(x y); e=(p"${a[@]}"q); echo ${e[0]} ${e[1]} ${#e[@]}"#);
+    assert_eq!(run.stdout, "px yq 2\n");
+}
+
+#[test]
+fn declare_with_array_literals() {
+    let run =
+        trsh("declare -A m=([a]=1 [b]=2); echo ${m[b]} ${#m[@]}; declare -a a=(x y); echo ${a[1]}");
+    assert_eq!(run.stdout, "2 2\ny\n");
+    assert_eq!(run.stderr, "");
+}
+
+#[test]
+fn export_assignments() {
+    let run = trsh("export F=v G; G=g; printenv F G; declare -x H=h; printenv H");
+    assert_eq!(run.stdout, "v\ng\nh\n");
+}
+
+#[test]
+fn local_outside_function() {
+    let run = trsh("local z=1; echo $?");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(run.stderr, "trsh: local: can only be used in a function\n");
+}
+
+#[test]
+fn unsupported_operator_is_bad_substitution() {
+    let run = trsh("echo ${X:-d}; echo $?; set -u; echo \"${X#a}\"");
+    assert_eq!(run.stdout, "1\n");
+    assert_eq!(
+        run.stderr,
+        "trsh: ${X:-d}: bad substitution\ntrsh: ${X#a}: bad substitution\n"
+    );
+    assert_eq!(run.code, Some(1));
+}